@@ -1,4 +1,7 @@
-use crate::config::{User, CONFIG, TOKEN_LENGTH};
+use crate::{
+    config::{User, CONFIG, TOKEN_LENGTH},
+    scope::Scope,
+};
 use axum::{
     body::{box_body, Body, BoxBody},
     http::{Request, Response, StatusCode},
@@ -11,7 +14,7 @@ const AUTH_HEADER: &str = "token";
 pub struct Token;
 
 impl AuthorizeRequest for Token {
-    type Output = User;
+    type Output = (User, Option<Scope>);
 
     type ResponseBody = BoxBody;
 
@@ -54,7 +57,10 @@ impl AuthorizeRequest for Token {
     }
 
     #[inline]
-    fn on_authorized<B>(&mut self, request: &mut Request<B>, output: Self::Output) {
-        let _ = request.extensions_mut().insert(output);
+    fn on_authorized<B>(&mut self, request: &mut Request<B>, (user, scope): Self::Output) {
+        let _ = request.extensions_mut().insert(user);
+        if let Some(scope) = scope {
+            let _ = request.extensions_mut().insert(scope);
+        }
     }
 }
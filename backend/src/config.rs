@@ -1,19 +1,30 @@
-use crate::socket::send_data_message;
+use crate::{
+    extension::QueryLimit,
+    limit::{LiverRateLimit, RateLimit},
+    scope::Scope,
+    socket::send_data_message,
+};
 use acfunlivedata_common::{
-    config::Config as CommonConfig,
-    message::{send_tool_message, DataCenterMessage, ToolMessage},
-    DIRECTORY_PATH,
+    config::Config as CommonConfig, message::DataCenterMessage, DIRECTORY_PATH,
 };
 use ahash::AHashMap;
 use anyhow::{bail, Result};
 use async_graphql::SimpleObject;
 use once_cell::sync::{Lazy, OnceCell};
 use rand::{distributions::Alphanumeric, thread_rng, Rng};
+use ring::{constant_time::verify_slices_are_equal, digest};
 use serde::{Deserialize, Serialize};
-use std::path::{Path, PathBuf};
+use std::{
+    path::{Path, PathBuf},
+    sync::atomic::{AtomicI64, Ordering},
+};
 use tokio::sync::RwLock;
 
 pub const TOKEN_LENGTH: usize = 32;
+// token的前8个字符用于查找，剩下的部分只保存加盐后的哈希值
+pub const TOKEN_ID_LENGTH: usize = 8;
+
+const SALT_LENGTH: usize = 16;
 
 const CONFIG_FILE: &str = "acfunlivedata_backend.json";
 
@@ -27,10 +38,18 @@ pub static CONFIG: OnceCell<RwLock<LiveConfig>> = OnceCell::new();
 
 pub type LiveConfig = CommonConfig<Config, &'static Path>;
 
-type Users = AHashMap<String, User>;
+pub const ADMIN_TOKEN_NAME: &str = "admin";
+pub const DEFAULT_TOKEN_NAME: &str = "default";
+
+type Users = AHashMap<String, TokenData>;
+
+#[inline]
+pub fn unix_time() -> i64 {
+    chrono::Utc::now().timestamp_millis()
+}
 
 #[inline]
-pub fn generate_token() -> String {
+fn generate_token() -> String {
     thread_rng()
         .sample_iter(&Alphanumeric)
         .take(TOKEN_LENGTH)
@@ -54,6 +73,14 @@ impl User {
     pub fn is_liver(&self, liver_uid: i64) -> bool {
         self == &User::Liver(liver_uid)
     }
+
+    #[inline]
+    pub fn liver_uid(&self) -> Option<i64> {
+        match self {
+            User::Admin => None,
+            User::Liver(liver_uid) => Some(*liver_uid),
+        }
+    }
 }
 
 #[derive(Clone, Debug, SimpleObject)]
@@ -62,16 +89,288 @@ pub struct TokenInfo {
     pub token: Option<String>,
 }
 
+// token的信息，不包含token本身，时间为毫秒
+#[derive(Clone, Debug, SimpleObject)]
+pub struct TokenMeta {
+    pub liver_uid: Option<i64>,
+    pub name: String,
+    pub create_time: i64,
+    pub expire_time: Option<i64>,
+    pub last_used_time: Option<i64>,
+    pub expired: bool,
+    pub scope: Option<Scope>,
+}
+
+// 都是hex编码
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+struct TokenHash {
+    salt: String,
+    hash: String,
+}
+
+impl TokenHash {
+    #[inline]
+    fn new(secret: &str) -> Self {
+        let salt: [u8; SALT_LENGTH] = thread_rng().gen();
+        Self {
+            salt: hex::encode(salt),
+            hash: hex::encode(hash_secret(&salt, secret)),
+        }
+    }
+
+    #[inline]
+    fn verify(&self, secret: &str) -> bool {
+        match (hex::decode(&self.salt), hex::decode(&self.hash)) {
+            (Ok(salt), Ok(hash)) => {
+                verify_slices_are_equal(hash_secret(&salt, secret).as_ref(), &hash).is_ok()
+            }
+            _ => {
+                log::error!("the token hash in config is invalid");
+                false
+            }
+        }
+    }
+}
+
+#[inline]
+fn hash_secret(salt: &[u8], secret: &str) -> digest::Digest {
+    let mut ctx = digest::Context::new(&digest::SHA256);
+    ctx.update(salt);
+    ctx.update(secret.as_bytes());
+    ctx.finish()
+}
+
+// 把token分成用于查找的id和secret
+#[inline]
+fn split_token(token: &str) -> Option<(&str, &str)> {
+    if token.len() == TOKEN_LENGTH && token.is_ascii() {
+        Some(token.split_at(TOKEN_ID_LENGTH))
+    } else {
+        None
+    }
+}
+
+// `hash`为None时是旧配置里的明文token
+#[derive(Clone, Debug, Deserialize, Serialize)]
+struct TokenRecord {
+    user: User,
+    name: String,
+    create_time: i64,
+    expire_time: Option<i64>,
+    last_used_time: Option<i64>,
+    #[serde(default)]
+    hash: Option<TokenHash>,
+    #[serde(default)]
+    scope: Option<Scope>,
+}
+
+// 兼容旧的配置，旧的配置里只有`User`
+#[derive(Clone, Debug, Deserialize)]
+#[serde(untagged)]
+enum TokenRepr {
+    Record(TokenRecord),
+    User(User),
+}
+
+// `last_used_time`只在内存里更新，保存配置时才写入文件，为0时表示没用过
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(from = "TokenRepr", into = "TokenRecord")]
+pub struct TokenData {
+    user: User,
+    name: String,
+    create_time: i64,
+    expire_time: Option<i64>,
+    last_used_time: AtomicI64,
+    hash: Option<TokenHash>,
+    scope: Option<Scope>,
+}
+
+impl TokenData {
+    #[inline]
+    fn new(user: User, name: String, expire_time: Option<i64>, scope: Option<Scope>) -> Self {
+        Self {
+            user,
+            name,
+            create_time: unix_time(),
+            expire_time,
+            last_used_time: AtomicI64::new(0),
+            hash: None,
+            scope,
+        }
+    }
+
+    #[inline]
+    fn is_expired(&self, now: i64) -> bool {
+        matches!(self.expire_time, Some(t) if t <= now)
+    }
+
+    #[inline]
+    fn meta(&self, now: i64) -> TokenMeta {
+        let last_used_time = self.last_used_time.load(Ordering::Relaxed);
+        TokenMeta {
+            liver_uid: self.user.liver_uid(),
+            name: self.name.clone(),
+            create_time: self.create_time,
+            expire_time: self.expire_time,
+            last_used_time: if last_used_time > 0 {
+                Some(last_used_time)
+            } else {
+                None
+            },
+            expired: self.is_expired(now),
+            scope: self.scope.clone(),
+        }
+    }
+}
+
+impl Clone for TokenData {
+    #[inline]
+    fn clone(&self) -> Self {
+        Self {
+            user: self.user,
+            name: self.name.clone(),
+            create_time: self.create_time,
+            expire_time: self.expire_time,
+            last_used_time: AtomicI64::new(self.last_used_time.load(Ordering::Relaxed)),
+            hash: self.hash.clone(),
+            scope: self.scope.clone(),
+        }
+    }
+}
+
+impl From<TokenRepr> for TokenData {
+    #[inline]
+    fn from(repr: TokenRepr) -> Self {
+        match repr {
+            TokenRepr::Record(record) => Self {
+                user: record.user,
+                name: record.name,
+                create_time: record.create_time,
+                expire_time: record.expire_time,
+                last_used_time: AtomicI64::new(record.last_used_time.unwrap_or_default()),
+                hash: record.hash,
+                scope: record.scope,
+            },
+            TokenRepr::User(user) => {
+                let name = if user.is_admin() {
+                    ADMIN_TOKEN_NAME
+                } else {
+                    DEFAULT_TOKEN_NAME
+                };
+                Self::new(user, name.to_string(), None, None)
+            }
+        }
+    }
+}
+
+impl From<TokenData> for TokenRecord {
+    #[inline]
+    fn from(data: TokenData) -> Self {
+        let last_used_time = data.last_used_time.into_inner();
+        Self {
+            user: data.user,
+            name: data.name,
+            create_time: data.create_time,
+            expire_time: data.expire_time,
+            last_used_time: if last_used_time > 0 {
+                Some(last_used_time)
+            } else {
+                None
+            },
+            hash: data.hash,
+            scope: data.scope,
+        }
+    }
+}
+
 #[derive(Clone, Debug, Default, Deserialize, Serialize)]
 pub struct Config {
     users: Users,
+    #[serde(default)]
+    default_rate_limit: RateLimit,
+    // 单独设置的主播的限制
+    #[serde(default)]
+    rate_limits: AHashMap<i64, RateLimit>,
+    #[serde(default)]
+    query_limit: QueryLimit,
 }
 
 impl Config {
+    #[inline]
+    pub fn query_limit(&self) -> QueryLimit {
+        self.query_limit
+    }
+
+    #[inline]
+    pub fn set_query_limit(&mut self, limit: QueryLimit) {
+        log::info!("set query limit: {:?}", limit);
+        self.query_limit = limit;
+    }
+
+    // admin不受限制
+    #[inline]
+    pub fn rate_limit(&self, user: User) -> Option<RateLimit> {
+        match user {
+            User::Admin => None,
+            User::Liver(liver_uid) => Some(
+                self.rate_limits
+                    .get(&liver_uid)
+                    .copied()
+                    .unwrap_or(self.default_rate_limit),
+            ),
+        }
+    }
+
+    #[inline]
+    pub fn default_rate_limit(&self) -> RateLimit {
+        self.default_rate_limit
+    }
+
+    pub fn liver_rate_limits(&self) -> Vec<LiverRateLimit> {
+        let mut limits = self
+            .rate_limits
+            .iter()
+            .map(|(liver_uid, limit)| LiverRateLimit {
+                liver_uid: *liver_uid,
+                limit: *limit,
+            })
+            .collect::<Vec<_>>();
+        limits.sort_unstable_by_key(|l| l.liver_uid);
+
+        limits
+    }
+
+    // `liver_uid`为None时设置默认的限制
+    pub fn set_rate_limit(&mut self, liver_uid: Option<i64>, limit: RateLimit) -> Result<()> {
+        if limit.requests_per_minute == 0 || limit.concurrent_queries == 0 {
+            bail!("the rate limit must be greater than 0");
+        }
+        match liver_uid {
+            Some(liver_uid) => {
+                if !self.contains_uid(liver_uid) {
+                    bail!("liver {} wasn't in config", liver_uid);
+                }
+                log::info!("set rate limit of liver {}: {:?}", liver_uid, limit);
+                let _ = self.rate_limits.insert(liver_uid, limit);
+            }
+            None => {
+                log::info!("set default rate limit: {:?}", limit);
+                self.default_rate_limit = limit;
+            }
+        }
+
+        Ok(())
+    }
+
+    #[inline]
+    pub fn delete_rate_limit(&mut self, liver_uid: i64) -> bool {
+        self.rate_limits.remove(&liver_uid).is_some()
+    }
+
     #[inline]
     pub fn contains_uid(&self, liver_uid: i64) -> bool {
         if liver_uid > 0 {
-            self.users.values().any(|i| i.is_liver(liver_uid))
+            self.users.values().any(|i| i.user.is_liver(liver_uid))
         } else {
             false
         }
@@ -79,63 +378,195 @@ impl Config {
 
     #[inline]
     pub fn contains_admin_token(&self) -> bool {
-        self.users.values().any(|i| i.is_admin())
+        self.users.values().any(|i| i.user.is_admin())
     }
 
+    // 过期的token返回None，同时更新最后使用的时间
     #[inline]
-    pub fn get(&self, token: &str) -> Option<User> {
-        self.users.get(token).copied()
+    pub fn get(&self, token: &str) -> Option<(User, Option<Scope>)> {
+        let (id, secret) = split_token(token)?;
+        let data = self.users.get(id)?;
+        if !data.hash.as_ref()?.verify(secret) {
+            return None;
+        }
+        let now = unix_time();
+        if data.is_expired(now) {
+            return None;
+        }
+        data.last_used_time.store(now, Ordering::Relaxed);
+
+        Some((data.user, data.scope.clone()))
     }
 
-    #[inline]
-    pub fn set_admin_token(&mut self, token: String) {
-        let _ = self.users.insert(token, User::Admin);
+    // 生成新的token并只保存哈希值，返回的token只能看到这一次
+    fn insert_token(&mut self, mut data: TokenData) -> String {
+        loop {
+            let token = generate_token();
+            let (id, secret) = token.split_at(TOKEN_ID_LENGTH);
+            if !self.users.contains_key(id) {
+                data.hash = Some(TokenHash::new(secret));
+                let _ = self.users.insert(id.to_string(), data);
+                return token;
+            }
+        }
+    }
+
+    // 把旧配置里的明文token换成哈希值，返回是否有改动
+    pub fn hash_plaintext_tokens(&mut self) -> bool {
+        let plaintext = self
+            .users
+            .iter()
+            .filter(|(_, i)| i.hash.is_none())
+            .map(|(t, _)| t.clone())
+            .collect::<Vec<_>>();
+        for token in &plaintext {
+            let mut data = self.users.remove(token).expect("failed to remove token");
+            match split_token(token) {
+                Some((id, secret)) if !self.users.contains_key(id) => {
+                    data.hash = Some(TokenHash::new(secret));
+                    let _ = self.users.insert(id.to_string(), data);
+                }
+                _ => log::warn!(
+                    "failed to hash token {} of {:?}, it is removed",
+                    data.name,
+                    data.user
+                ),
+            }
+        }
+        if !plaintext.is_empty() {
+            log::info!("hashed {} plaintext tokens in config", plaintext.len());
+        }
+
+        !plaintext.is_empty()
     }
 
     #[inline]
-    pub fn set_liver_token(&mut self, token: String, liver_uid: i64) -> Result<()> {
-        if liver_uid > 0 {
-            let _ = self.users.insert(token, User::Liver(liver_uid));
-            Ok(())
+    pub fn set_admin_token(&mut self) -> String {
+        self.insert_token(TokenData::new(
+            User::Admin,
+            ADMIN_TOKEN_NAME.to_string(),
+            None,
+            None,
+        ))
+    }
+
+    // `liver_uid`为None时列出所有token
+    pub fn tokens(&self, liver_uid: Option<i64>) -> Vec<TokenMeta> {
+        let now = unix_time();
+        let mut tokens = self
+            .users
+            .values()
+            .filter(|i| match liver_uid {
+                Some(liver_uid) => i.user.is_liver(liver_uid),
+                None => true,
+            })
+            .map(|i| i.meta(now))
+            .collect::<Vec<_>>();
+        tokens.sort_unstable_by(|a, b| {
+            (a.liver_uid, a.create_time, &a.name).cmp(&(b.liver_uid, b.create_time, &b.name))
+        });
+
+        tokens
+    }
+
+    pub fn add_token(
+        &mut self,
+        liver_uid: i64,
+        name: String,
+        expire_time: Option<i64>,
+        scope: Option<Scope>,
+    ) -> Result<(String, TokenMeta)> {
+        if liver_uid <= 0 {
+            bail!("liver uid {} is less than 1", liver_uid);
+        }
+        if !self.contains_uid(liver_uid) {
+            bail!("liver {} wasn't in config", liver_uid);
+        }
+        if name.is_empty() {
+            bail!("the token name is empty");
+        }
+        let now = unix_time();
+        if let Some(expire_time) = expire_time {
+            if expire_time <= now {
+                bail!(
+                    "the expire time {} is earlier than now {}",
+                    expire_time,
+                    now
+                );
+            }
+        }
+        if self
+            .users
+            .values()
+            .any(|i| i.user.is_liver(liver_uid) && i.name == name)
+        {
+            bail!("liver {} already has a token named {}", liver_uid, name);
+        }
+        log::info!("add token {} of liver {}", name, liver_uid);
+        let data = TokenData::new(User::Liver(liver_uid), name, expire_time, scope);
+        let meta = data.meta(now);
+        let token = self.insert_token(data);
+
+        Ok((token, meta))
+    }
+
+    pub fn revoke_token(&mut self, liver_uid: i64, name: &str) -> bool {
+        let len = self.users.len();
+        self.users
+            .retain(|_, i| !(i.user.is_liver(liver_uid) && i.name == name));
+        let exist = self.users.len() != len;
+        if exist {
+            log::info!("revoke token {} of liver {}", name, liver_uid);
         } else {
-            Err(anyhow::anyhow!("liver uid {} is less than 1", liver_uid))
+            log::warn!("liver {} doesn't have a token named {}", liver_uid, name);
         }
+
+        exist
     }
 
+    // 只重新生成token，保留名字、过期时间和权限，不通知数据中心
+    pub fn regenerate_token(&mut self, liver_uid: i64, name: &str) -> Result<String> {
+        let old = match self
+            .users
+            .iter()
+            .find(|(_, i)| i.user.is_liver(liver_uid) && i.name == name)
+        {
+            Some((token, _)) => token.clone(),
+            None => bail!("liver {} doesn't have a token named {}", liver_uid, name),
+        };
+        log::info!("regenerate token {} of liver {}", name, liver_uid);
+        let data = self.users.remove(&old).expect("failed to remove token");
+
+        Ok(self.insert_token(TokenData::new(
+            data.user,
+            data.name,
+            data.expire_time,
+            data.scope,
+        )))
+    }
+
+    // 已经添加过的主播不会重新生成token
     pub async fn add_liver(&mut self, liver_uid: i64, tool: bool) -> Result<TokenInfo> {
         if liver_uid > 0 {
             log::info!("add liver {}", liver_uid);
-            let token = generate_token();
-            let mut exist = false;
-            if self.contains_uid(liver_uid) {
-                exist = true;
-                log::warn!(
-                    "already added liver {} before, generating new token",
-                    liver_uid
-                );
-                self.users.retain(|_, i| !i.is_liver(liver_uid));
-                if tool {
-                    send_tool_message(&ToolMessage::BackendAddLiver(
-                        liver_uid,
-                        true,
-                        token.clone(),
-                    ))
-                    .await;
-                }
-            } else if tool {
-                send_tool_message(&ToolMessage::BackendAddLiver(
-                    liver_uid,
-                    false,
-                    token.clone(),
-                ))
-                .await;
-            }
             if !tool {
-                send_data_message(&DataCenterMessage::AddLiver(liver_uid, false)).await;
+                send_data_message(&DataCenterMessage::AddLiver(liver_uid)).await;
+            }
+            if self.contains_uid(liver_uid) {
+                log::warn!("already added liver {} before", liver_uid);
+                return Ok(TokenInfo {
+                    exist: true,
+                    token: None,
+                });
             }
-            self.set_liver_token(token.clone(), liver_uid)?;
+            let token = self.insert_token(TokenData::new(
+                User::Liver(liver_uid),
+                DEFAULT_TOKEN_NAME.to_string(),
+                None,
+                None,
+            ));
             Ok(TokenInfo {
-                exist,
+                exist: false,
                 token: Some(token),
             })
         } else {
@@ -149,18 +580,13 @@ impl Config {
             let mut exist = false;
             if self.contains_uid(liver_uid) {
                 exist = true;
-                self.users.retain(|_, i| !i.is_liver(liver_uid));
-                if tool {
-                    send_tool_message(&ToolMessage::BackendDeleteLiver(liver_uid, true)).await;
-                }
+                self.users.retain(|_, i| !i.user.is_liver(liver_uid));
+                let _ = self.rate_limits.remove(&liver_uid);
             } else {
                 log::warn!("liver {} wasn't in config", liver_uid);
-                if tool {
-                    send_tool_message(&ToolMessage::BackendDeleteLiver(liver_uid, false)).await;
-                }
             }
             if !tool {
-                send_data_message(&DataCenterMessage::DeleteLiver(liver_uid, false)).await;
+                send_data_message(&DataCenterMessage::DeleteLiver(liver_uid)).await;
             }
             Ok(TokenInfo { exist, token: None })
         } else {
@@ -168,3 +594,70 @@ impl Config {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ADMIN_TOKEN: &str = "abcdefgh0123456789ABCDEFGHIJKLMN";
+    const LIVER_TOKEN: &str = "ijklmnop0123456789ABCDEFGHIJKLMN";
+
+    #[test]
+    fn test_token_hash() {
+        let hash = TokenHash::new("0123456789ABCDEFGHIJKLMN");
+        assert!(hash.verify("0123456789ABCDEFGHIJKLMN"));
+        assert!(!hash.verify("0123456789ABCDEFGHIJKLMn"));
+        assert!(!hash.verify(""));
+        // 相同的secret用不同的盐
+        assert_ne!(hash, TokenHash::new("0123456789ABCDEFGHIJKLMN"));
+
+        let invalid = TokenHash {
+            salt: "xyz".to_string(),
+            hash: hash.hash,
+        };
+        assert!(!invalid.verify("0123456789ABCDEFGHIJKLMN"));
+    }
+
+    #[test]
+    fn test_split_token() {
+        assert_eq!(
+            split_token(ADMIN_TOKEN),
+            Some(("abcdefgh", "0123456789ABCDEFGHIJKLMN"))
+        );
+        assert_eq!(split_token(&ADMIN_TOKEN[1..]), None);
+        assert_eq!(split_token(&format!("{}a", ADMIN_TOKEN)), None);
+        assert_eq!(split_token("一二abcdefghijklmnopqrstuvwxyz"), None);
+    }
+
+    #[test]
+    fn test_hash_plaintext_tokens() -> Result<()> {
+        let json = format!(
+            r#"{{"users":{{"{}":"Admin","{}":{{"Liver":123}}}}}}"#,
+            ADMIN_TOKEN, LIVER_TOKEN
+        );
+        let mut config: Config = serde_json::from_str(&json)?;
+        // 迁移前明文token不能使用
+        assert_eq!(config.get(ADMIN_TOKEN), None);
+
+        assert!(config.hash_plaintext_tokens());
+        assert!(!config.hash_plaintext_tokens());
+        assert!(config.users.contains_key("abcdefgh"));
+        assert!(!config.users.contains_key(ADMIN_TOKEN));
+        assert_eq!(config.get(ADMIN_TOKEN), Some((User::Admin, None)));
+        assert_eq!(config.get(LIVER_TOKEN), Some((User::Liver(123), None)));
+        assert_eq!(config.get("ijklmnop0123456789ABCDEFGHIJKLMn"), None);
+        assert_eq!(config.get("qrstuvwx0123456789ABCDEFGHIJKLMN"), None);
+
+        let tokens = config.tokens(Some(123));
+        assert_eq!(tokens.len(), 1);
+        assert_eq!(tokens[0].name, DEFAULT_TOKEN_NAME);
+        assert!(tokens[0].last_used_time.is_some());
+
+        // 保存后再读取仍然能使用
+        let json = serde_json::to_string(&config)?;
+        let config: Config = serde_json::from_str(&json)?;
+        assert_eq!(config.get(LIVER_TOKEN), Some((User::Liver(123), None)));
+
+        Ok(())
+    }
+}
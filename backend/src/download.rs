@@ -1,7 +1,12 @@
-use crate::config::User;
+use crate::{
+    config::{User, CONFIG},
+    export::TABLES,
+    limit::LIMITER,
+    scope::Scope,
+    sql::*,
+};
 use acfunlivedata_common::{
-    client::build_client,
-    database::{liver_db_file, liver_db_path},
+    database::{liver_db_path, BUSY_TIMEOUT},
     file_exist, DIRECTORY_PATH,
 };
 use anyhow::{bail, Error, Result};
@@ -9,22 +14,29 @@ use axum::{
     body::Body,
     http::{
         header::{HeaderMap, HeaderValue, CONTENT_DISPOSITION, CONTENT_LENGTH},
-        Request,
+        Request, StatusCode,
     },
 };
-use cached::proc_macro::cached;
 use once_cell::sync::Lazy;
+use rusqlite::{Connection, OpenFlags, ToSql};
+use serde::Deserialize;
 use std::{
     convert::TryInto,
     future::Future,
-    path::PathBuf,
+    io,
+    path::{Path, PathBuf},
     pin::Pin,
+    sync::atomic::{AtomicU64, Ordering},
     task::{Context, Poll},
 };
 use tokio::fs;
 use tower::Service;
 
 const TEMP_DIR: &str = "temp";
+// 所有临时数据库文件的总大小上限
+const MAX_TEMP_SIZE: u64 = 10 * 1024 * 1024 * 1024;
+
+static TEMP_SIZE: AtomicU64 = AtomicU64::new(0);
 
 pub static TEMP_DIRECTORY: Lazy<PathBuf> = Lazy::new(|| {
     let mut path = DIRECTORY_PATH.clone();
@@ -32,25 +44,113 @@ pub static TEMP_DIRECTORY: Lazy<PathBuf> = Lazy::new(|| {
     path
 });
 
-#[cached(size = 10, time = 10, result = true)]
-#[inline]
-async fn is_live(liver_uid: i64) -> Result<bool> {
-    let api_client = build_client().await?;
-    let info = api_client.get_user_live_info(liver_uid).await?;
-    if let Some(data) = info.live_data {
-        Ok(!data.live_id.is_empty())
-    } else {
-        Ok(false)
-    }
-}
-
+// 同一个主播可能同时有多个下载
 #[inline]
 fn temp_db_path(liver_uid: i64) -> PathBuf {
     let mut path = TEMP_DIRECTORY.clone();
-    path.push(liver_db_file(liver_uid));
+    path.push(format!("{}-{:016x}.db", liver_uid, rand::random::<u64>()));
     path
 }
 
+#[derive(Clone, Copy, Debug, thiserror::Error)]
+#[error("the temp directory is full, used {used} bytes, need {need} bytes")]
+pub struct TempDirFull {
+    used: u64,
+    need: u64,
+}
+
+impl TempDirFull {
+    #[inline]
+    pub fn response(&self) -> (StatusCode, HeaderMap, String) {
+        (
+            StatusCode::SERVICE_UNAVAILABLE,
+            HeaderMap::new(),
+            self.to_string(),
+        )
+    }
+}
+
+// 客户端的错误，返回对应的状态码和错误信息
+#[derive(Clone, Debug, thiserror::Error)]
+pub enum DownloadError {
+    #[error("the admin token can't download database, use the token of a liver")]
+    Admin,
+    #[error("the token of liver {0} can't download database")]
+    Forbidden(i64),
+    #[error("invalid query: {0}")]
+    InvalidQuery(String),
+    #[error("start {start} is bigger than end {end}")]
+    InvalidRange { start: i64, end: i64 },
+    #[error("database file of liver {0} doesn't exist")]
+    NotFound(i64),
+}
+
+impl DownloadError {
+    #[inline]
+    pub fn response(&self) -> (StatusCode, HeaderMap, String) {
+        let status = match self {
+            DownloadError::Admin | DownloadError::Forbidden(_) => StatusCode::FORBIDDEN,
+            DownloadError::InvalidQuery(_) | DownloadError::InvalidRange { .. } => {
+                StatusCode::BAD_REQUEST
+            }
+            DownloadError::NotFound(_) => StatusCode::NOT_FOUND,
+        };
+
+        (status, HeaderMap::new(), self.to_string())
+    }
+}
+
+// 临时数据库文件，drop时删除文件并释放占用的空间
+#[derive(Debug)]
+pub struct TempFile {
+    path: PathBuf,
+    size: u64,
+}
+
+impl TempFile {
+    // `size`为预计的文件大小
+    fn new(liver_uid: i64, size: u64) -> Result<Self, TempDirFull> {
+        let _ = TEMP_SIZE
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |used| {
+                used.checked_add(size).filter(|n| *n <= MAX_TEMP_SIZE)
+            })
+            .map_err(|used| TempDirFull { used, need: size })?;
+
+        Ok(Self {
+            path: temp_db_path(liver_uid),
+            size,
+        })
+    }
+
+    #[inline]
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl Drop for TempFile {
+    fn drop(&mut self) {
+        if let Err(e) = std::fs::remove_file(&self.path) {
+            if e.kind() != io::ErrorKind::NotFound {
+                log::warn!("failed to remove temp file {:?}: {}", self.path, e);
+            }
+        }
+        let _ = TEMP_SIZE.fetch_sub(self.size, Ordering::SeqCst);
+    }
+}
+
+// 删除上次运行时遗留的临时文件
+pub async fn clean_temp_directory() -> Result<()> {
+    let mut dir = fs::read_dir(&*TEMP_DIRECTORY).await?;
+    while let Some(entry) = dir.next_entry().await? {
+        if entry.file_type().await?.is_file() {
+            fs::remove_file(entry.path()).await?;
+        }
+    }
+
+    Ok(())
+}
+
 #[inline]
 fn db_filename(liver_uid: i64) -> String {
     let date = chrono::Local::now()
@@ -60,14 +160,83 @@ fn db_filename(liver_uid: i64) -> String {
     format!("{}-{}.db", liver_uid, date)
 }
 
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct DownloadParams {
+    live_id: Option<String>,
+    start: Option<i64>,
+    end: Option<i64>,
+}
+
+impl DownloadParams {
+    #[inline]
+    fn is_trimmed(&self) -> bool {
+        self.live_id.is_some() || self.start.is_some() || self.end.is_some()
+    }
+}
+
+// VACUUM INTO在一个读事务里完成，数据中心同时写入也能得到一致的数据库
+fn backup(db_path: PathBuf, temp_path: PathBuf, params: DownloadParams) -> Result<()> {
+    let conn = Connection::open_with_flags(
+        db_path,
+        OpenFlags::SQLITE_OPEN_READ_ONLY | OpenFlags::SQLITE_OPEN_NO_MUTEX,
+    )?;
+    conn.busy_timeout(BUSY_TIMEOUT)?;
+    let temp = match temp_path.to_str() {
+        Some(temp) => temp,
+        None => bail!("temp path {:?} isn't valid UTF-8", temp_path),
+    };
+    let _ = conn.execute(VACUUM_INTO, &[&temp as &dyn ToSql])?;
+    drop(conn);
+
+    // 原数据库是WAL模式，下载的数据库改回单文件的rollback journal
+    let mut conn = Connection::open(&temp_path)?;
+    conn.execute_batch(JOURNAL_MODE_DELETE)?;
+    if !params.is_trimmed() {
+        return Ok(());
+    }
+
+    // 删除不符合条件的行
+    let tx = conn.transaction()?;
+    for table in &TABLES {
+        let mut sql = DELETE_FROM.to_string();
+        sql += table.name;
+        sql += WHERE;
+        sql += NOT;
+        sql += LEFT_PARENTHESES;
+        let mut conditions: Vec<&str> = Vec::new();
+        let mut sql_params: Vec<&dyn ToSql> = Vec::new();
+        if let Some(live_id) = &params.live_id {
+            conditions.push(LIVE_ID);
+            sql_params.push(live_id);
+        }
+        if let Some(start) = &params.start {
+            conditions.push(table.start);
+            sql_params.push(start);
+        }
+        if let Some(end) = &params.end {
+            conditions.push(table.end);
+            sql_params.push(end);
+        }
+        sql += &conditions.join(AND);
+        sql += RIGHT_PARENTHESES;
+        sql += SEMICOLON;
+        let _ = tx.execute(&sql, sql_params.as_slice())?;
+    }
+    tx.commit()?;
+    conn.execute_batch(VACUUM)?;
+
+    Ok(())
+}
+
 pub type DownloadFuture =
-    Pin<Box<dyn Future<Output = std::result::Result<(PathBuf, HeaderMap), Error>> + Send>>;
+    Pin<Box<dyn Future<Output = std::result::Result<(TempFile, HeaderMap), Error>> + Send>>;
 
 #[derive(Clone, Copy, Debug)]
 pub struct Download;
 
 impl Service<Request<Body>> for Download {
-    type Response = (PathBuf, HeaderMap);
+    type Response = (TempFile, HeaderMap);
 
     type Error = Error;
 
@@ -82,21 +251,47 @@ impl Service<Request<Body>> for Download {
         Box::pin(async move {
             let liver_uid = match req.extensions().get::<User>() {
                 Some(User::Liver(liver_uid)) => *liver_uid,
-                Some(User::Admin) => bail!("this is an admin token"),
+                Some(User::Admin) => bail!(DownloadError::Admin),
                 None => panic!("no User in Request extensions"),
             };
-            log::info!("[{}] start preparing downloading database", liver_uid);
-            if is_live(liver_uid).await? {
-                bail!("liver {} is living", liver_uid);
+            if let Some(scope) = req.extensions().get::<Scope>() {
+                if !scope.download {
+                    bail!(DownloadError::Forbidden(liver_uid));
+                }
             }
+            let limit = CONFIG
+                .get()
+                .expect("failed to get CONFIG")
+                .read()
+                .await
+                .rate_limit(User::Liver(liver_uid));
+            // 只在准备数据库文件时占用并发数
+            let _permit = LIMITER.acquire(User::Liver(liver_uid), limit)?;
+            let params: DownloadParams = match req.uri().query() {
+                Some(query) => serde_urlencoded::from_str(query)
+                    .map_err(|e| DownloadError::InvalidQuery(e.to_string()))?,
+                None => DownloadParams::default(),
+            };
+            if let (Some(start), Some(end)) = (params.start, params.end) {
+                if start > end {
+                    bail!(DownloadError::InvalidRange { start, end });
+                }
+            }
+            log::info!("[{}] start preparing downloading database", liver_uid);
             let db_path = liver_db_path(liver_uid);
             if !file_exist(&db_path).await {
-                bail!("database file of liver {} doesn't exist", liver_uid);
+                bail!(DownloadError::NotFound(liver_uid));
             }
 
-            let temp_path = temp_db_path(liver_uid);
-            let _ = fs::copy(&db_path, &temp_path).await?;
-            let metadata = fs::metadata(&temp_path).await?;
+            let size = fs::metadata(&db_path).await?.len();
+            let temp = TempFile::new(liver_uid, size)?;
+            // 请求被取消时backup还在运行，临时文件在backup结束后才能删除
+            let temp = tokio::task::spawn_blocking(move || {
+                backup(db_path, temp.path().to_path_buf(), params)?;
+                Ok::<_, Error>(temp)
+            })
+            .await??;
+            let metadata = fs::metadata(temp.path()).await?;
             let mut headers = HeaderMap::<HeaderValue>::with_capacity(2);
             let _ = headers.insert(CONTENT_LENGTH, metadata.len().into());
             let _ = headers.insert(
@@ -104,7 +299,7 @@ impl Service<Request<Body>> for Download {
                 format!(r#"attachment; filename="{}""#, db_filename(liver_uid)).try_into()?,
             );
 
-            Ok((temp_path, headers))
+            Ok((temp, headers))
         })
     }
 }
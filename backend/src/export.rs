@@ -0,0 +1,511 @@
+use crate::{
+    config::{User, CONFIG},
+    limit::LIMITER,
+    pool::RusqliteConnectionManager,
+    scope::{Scope, Table},
+    sql::*,
+    sqlite::connect,
+};
+use acfunlivedata_common::database::liver_db_path;
+use anyhow::Result;
+use axum::{
+    body::Body,
+    extract,
+    http::{
+        header::{HeaderMap, CONTENT_DISPOSITION, CONTENT_TYPE},
+        Response, StatusCode,
+    },
+};
+use bb8::Pool;
+use hyper::body::Bytes;
+use rusqlite::{types::ValueRef, ToSql};
+use serde::Deserialize;
+use std::{borrow::Cow, io, iter, mem};
+use tokio::sync::mpsc;
+
+// 每次发送的数据大小
+const CHUNK_SIZE: usize = 64 * 1024;
+// 客户端读取较慢时查询会阻塞，内存占用最多为CHUNK_SIZE * CHANNEL_CAPACITY
+const CHANNEL_CAPACITY: usize = 16;
+
+type ErrorResponse = (StatusCode, HeaderMap, String);
+
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportFormat {
+    Ndjson,
+    Csv,
+}
+
+impl Default for ExportFormat {
+    #[inline]
+    fn default() -> Self {
+        Self::Ndjson
+    }
+}
+
+impl ExportFormat {
+    #[inline]
+    fn content_type(self) -> &'static str {
+        match self {
+            Self::Ndjson => "application/x-ndjson",
+            Self::Csv => "text/csv; charset=utf-8",
+        }
+    }
+
+    #[inline]
+    fn extension(self) -> &'static str {
+        match self {
+            Self::Ndjson => "ndjson",
+            Self::Csv => "csv",
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExportParams {
+    table: String,
+    #[serde(default)]
+    format: ExportFormat,
+    live_id: Option<String>,
+    start: Option<i64>,
+    end: Option<i64>,
+    liver_uid: Option<i64>,
+}
+
+// `start`和`end`为时间的查询条件
+#[derive(Clone, Copy, Debug)]
+pub struct ExportTable {
+    pub name: &'static str,
+    pub scope: Table,
+    pub select: &'static str,
+    pub start: &'static str,
+    pub end: &'static str,
+}
+
+impl ExportTable {
+    #[inline]
+    const fn new(
+        name: &'static str,
+        scope: Table,
+        select: &'static str,
+        start: &'static str,
+        end: &'static str,
+    ) -> Self {
+        Self {
+            name,
+            scope,
+            select,
+            start,
+            end,
+        }
+    }
+
+    // `table`为数据库的表名
+    #[inline]
+    fn from_name(table: &str) -> Option<Self> {
+        TABLES.iter().find(|t| t.name == table).copied()
+    }
+}
+
+// 主播数据库里的所有表
+pub const TABLES: [ExportTable; 17] = [
+    ExportTable::new(
+        "live_info",
+        Table::LiveInfo,
+        SELECT_LIVE_INFO,
+        START_TIME_START,
+        START_TIME_END,
+    ),
+    ExportTable::new(
+        "title",
+        Table::Title,
+        SELECT_TITLE,
+        SAVE_TIME_START,
+        SAVE_TIME_END,
+    ),
+    ExportTable::new(
+        "liver_info",
+        Table::LiverInfo,
+        SELECT_LIVER_INFO,
+        SAVE_TIME_START,
+        SAVE_TIME_END,
+    ),
+    ExportTable::new(
+        "summary",
+        Table::Summary,
+        SELECT_SUMMARY,
+        SAVE_TIME_START,
+        SAVE_TIME_END,
+    ),
+    ExportTable::new(
+        "comment",
+        Table::Comment,
+        SELECT_COMMENT,
+        SEND_TIME_START,
+        SEND_TIME_END,
+    ),
+    ExportTable::new(
+        "follow",
+        Table::Follow,
+        SELECT_FOLLOW,
+        SEND_TIME_START,
+        SEND_TIME_END,
+    ),
+    ExportTable::new(
+        "gift",
+        Table::Gift,
+        SELECT_GIFT,
+        SEND_TIME_START,
+        SEND_TIME_END,
+    ),
+    ExportTable::new(
+        "join_club",
+        Table::JoinClub,
+        SELECT_JOIN_CLUB,
+        JOIN_TIME_START,
+        JOIN_TIME_END,
+    ),
+    ExportTable::new(
+        "watching_count",
+        Table::WatchingCount,
+        SELECT_WATCHING_COUNT,
+        SAVE_TIME_START,
+        SAVE_TIME_END,
+    ),
+    ExportTable::new(
+        "redpack",
+        Table::Redpack,
+        SELECT_REDPACK,
+        SAVE_TIME_START,
+        SAVE_TIME_END,
+    ),
+    ExportTable::new(
+        "chat_call",
+        Table::Chat,
+        SELECT_CHAT_CALL,
+        CALL_TIME_START,
+        CALL_TIME_END,
+    ),
+    ExportTable::new(
+        "chat_ready",
+        Table::Chat,
+        SELECT_CHAT_READY,
+        SAVE_TIME_START,
+        SAVE_TIME_END,
+    ),
+    ExportTable::new(
+        "chat_end",
+        Table::Chat,
+        SELECT_CHAT_END,
+        SAVE_TIME_START,
+        SAVE_TIME_END,
+    ),
+    ExportTable::new(
+        "author_chat_call",
+        Table::AuthorChat,
+        SELECT_AUTHOR_CHAT_CALL,
+        CALL_TIME_START,
+        CALL_TIME_END,
+    ),
+    ExportTable::new(
+        "author_chat_ready",
+        Table::AuthorChat,
+        SELECT_AUTHOR_CHAT_READY,
+        SAVE_TIME_START,
+        SAVE_TIME_END,
+    ),
+    ExportTable::new(
+        "author_chat_end",
+        Table::AuthorChat,
+        SELECT_AUTHOR_CHAT_END,
+        SAVE_TIME_START,
+        SAVE_TIME_END,
+    ),
+    ExportTable::new(
+        "author_chat_change_sound_config",
+        Table::AuthorChat,
+        SELECT_AUTHOR_CHAT_CHANGE_SOUND_CONFIG,
+        SAVE_TIME_START,
+        SAVE_TIME_END,
+    ),
+];
+
+#[inline]
+fn error_response(status: StatusCode, message: impl Into<String>) -> ErrorResponse {
+    (status, HeaderMap::new(), message.into())
+}
+
+pub async fn export_handler(
+    user: extract::Extension<User>,
+    scope: Option<extract::Extension<Scope>>,
+    params: extract::Query<ExportParams>,
+) -> Result<Response<Body>, ErrorResponse> {
+    let params = params.0;
+    let table = ExportTable::from_name(&params.table).ok_or_else(|| {
+        error_response(
+            StatusCode::BAD_REQUEST,
+            format!("unknown table {}", params.table),
+        )
+    })?;
+    if let Some(scope) = scope {
+        if !scope.0.contains(table.scope) {
+            return Err(error_response(
+                StatusCode::FORBIDDEN,
+                format!(
+                    "the token doesn't have the permission to read {:?}",
+                    table.scope
+                ),
+            ));
+        }
+    }
+    let liver_uid = match (user.0, params.liver_uid) {
+        (User::Admin, None) => {
+            return Err(error_response(
+                StatusCode::BAD_REQUEST,
+                "admin token need liverUid",
+            ))
+        }
+        (User::Admin, Some(liver_uid)) => liver_uid,
+        (User::Liver(liver_uid), None) => liver_uid,
+        (User::Liver(_), Some(_)) => {
+            return Err(error_response(
+                StatusCode::BAD_REQUEST,
+                "liver token don't need liverUid",
+            ))
+        }
+    };
+    if let (Some(start), Some(end)) = (params.start, params.end) {
+        if start > end {
+            return Err(error_response(
+                StatusCode::BAD_REQUEST,
+                format!("start {} is bigger than end {}", start, end),
+            ));
+        }
+    }
+
+    let limit = CONFIG
+        .get()
+        .expect("failed to get CONFIG")
+        .read()
+        .await
+        .rate_limit(user.0);
+    let permit = LIMITER.acquire(user.0, limit).map_err(|e| e.response())?;
+    let pool = connect(liver_db_path(liver_uid)).await.map_err(|e| {
+        error_response(
+            StatusCode::NOT_FOUND,
+            format!("failed to open the database of liver {}: {}", liver_uid, e),
+        )
+    })?;
+    log::info!("[{}] start exporting table {}", liver_uid, params.table);
+
+    let filename = format!(
+        "{}-{}.{}",
+        liver_uid,
+        params.table,
+        params.format.extension()
+    );
+    let content_type = params.format.content_type();
+    let (tx, rx) = mpsc::channel(CHANNEL_CAPACITY);
+    let _ = tokio::task::spawn_blocking(move || {
+        // 导出结束后才释放并发数
+        let _permit = permit;
+        if let Err(e) = export_rows(&pool, table, &params, &tx) {
+            log::error!(
+                "[{}] failed to export table {}: {}",
+                liver_uid,
+                params.table,
+                e
+            );
+            let _ = tx.blocking_send(Err(io::Error::new(io::ErrorKind::Other, e.to_string())));
+        }
+    });
+    let stream = futures::stream::unfold(rx, |mut rx| async move {
+        rx.recv().await.map(|chunk| (chunk, rx))
+    });
+
+    Response::builder()
+        .header(CONTENT_TYPE, content_type)
+        .header(
+            CONTENT_DISPOSITION,
+            format!(r#"attachment; filename="{}""#, filename),
+        )
+        .body(Body::wrap_stream(stream))
+        .map_err(|e| error_response(StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))
+}
+
+// 逐行查询并按CHUNK_SIZE分块发送，客户端断开时停止查询
+fn export_rows(
+    pool: &Pool<RusqliteConnectionManager>,
+    table: ExportTable,
+    params: &ExportParams,
+    tx: &mpsc::Sender<io::Result<Bytes>>,
+) -> Result<()> {
+    let mut sql = table.select.to_string();
+    let mut sql_params: Vec<&dyn ToSql> = Vec::new();
+    let mut where_or_and = iter::once(WHERE).chain(iter::repeat(AND));
+    if let Some(live_id) = &params.live_id {
+        sql += where_or_and.next().unwrap();
+        sql += LIVE_ID;
+        sql_params.push(live_id);
+    }
+    if let Some(start) = &params.start {
+        sql += where_or_and.next().unwrap();
+        sql += table.start;
+        sql_params.push(start);
+    }
+    if let Some(end) = &params.end {
+        sql += where_or_and.next().unwrap();
+        sql += table.end;
+        sql_params.push(end);
+    }
+    sql += SEMICOLON;
+
+    let conn = futures::executor::block_on(pool.get())?;
+    let mut stmt = conn.prepare(&sql)?;
+    // 最后一列是rowid，不导出
+    let columns = stmt
+        .column_names()
+        .into_iter()
+        .take(stmt.column_count() - 1)
+        .map(String::from)
+        .collect::<Vec<_>>();
+    let mut buf = Vec::with_capacity(CHUNK_SIZE);
+    if params.format == ExportFormat::Csv {
+        write_csv_record(&mut buf, columns.iter().map(|c| Cow::Borrowed(c.as_str())));
+    }
+
+    let mut rows = stmt.query(sql_params.as_slice())?;
+    while let Some(row) = rows.next()? {
+        let values = (0..columns.len())
+            .map(|i| row.get_ref(i))
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        match params.format {
+            ExportFormat::Ndjson => write_json_object(&mut buf, &columns, &values)?,
+            ExportFormat::Csv => write_csv_record(&mut buf, values.iter().map(|v| csv_value(*v))),
+        }
+        if buf.len() >= CHUNK_SIZE {
+            let chunk = mem::replace(&mut buf, Vec::with_capacity(CHUNK_SIZE));
+            if tx.blocking_send(Ok(chunk.into())).is_err() {
+                log::info!("client stopped exporting table {}", params.table);
+                return Ok(());
+            }
+        }
+    }
+    if !buf.is_empty() {
+        let _ = tx.blocking_send(Ok(buf.into()));
+    }
+
+    Ok(())
+}
+
+fn write_json_object(buf: &mut Vec<u8>, columns: &[String], values: &[ValueRef<'_>]) -> Result<()> {
+    buf.push(b'{');
+    for (i, (column, value)) in columns.iter().zip(values).enumerate() {
+        if i != 0 {
+            buf.push(b',');
+        }
+        serde_json::to_writer(&mut *buf, column)?;
+        buf.push(b':');
+        match value {
+            ValueRef::Null => buf.extend_from_slice(b"null"),
+            ValueRef::Integer(n) => buf.extend_from_slice(n.to_string().as_bytes()),
+            ValueRef::Real(f) => serde_json::to_writer(&mut *buf, f)?,
+            ValueRef::Text(s) => serde_json::to_writer(&mut *buf, &String::from_utf8_lossy(s))?,
+            ValueRef::Blob(b) => serde_json::to_writer(&mut *buf, &hex::encode(b))?,
+        }
+    }
+    buf.extend_from_slice(b"}\n");
+
+    Ok(())
+}
+
+#[inline]
+fn csv_value(value: ValueRef<'_>) -> Cow<'_, str> {
+    match value {
+        ValueRef::Null => Cow::Borrowed(""),
+        ValueRef::Integer(n) => Cow::Owned(n.to_string()),
+        ValueRef::Real(f) => Cow::Owned(f.to_string()),
+        ValueRef::Text(s) => String::from_utf8_lossy(s),
+        ValueRef::Blob(b) => Cow::Owned(hex::encode(b)),
+    }
+}
+
+// 按RFC 4180转义
+fn write_csv_record<'a>(buf: &mut Vec<u8>, fields: impl Iterator<Item = Cow<'a, str>>) {
+    for (i, field) in fields.enumerate() {
+        if i != 0 {
+            buf.push(b',');
+        }
+        if field.contains(&[',', '"', '\r', '\n'][..]) {
+            buf.push(b'"');
+            buf.extend_from_slice(field.replace('"', r#""""#).as_bytes());
+            buf.push(b'"');
+        } else {
+            buf.extend_from_slice(field.as_bytes());
+        }
+    }
+    buf.extend_from_slice(b"\r\n");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn values() -> Vec<ValueRef<'static>> {
+        vec![
+            ValueRef::Integer(-1),
+            ValueRef::Real(1.5),
+            ValueRef::Text(b"a,b"),
+            ValueRef::Text(br#"say "hi""#),
+            ValueRef::Text(b"line1\r\nline2\n"),
+            ValueRef::Null,
+            ValueRef::Blob(&[0x00, 0xab, 0xff]),
+        ]
+    }
+
+    #[test]
+    fn test_write_csv_record() {
+        let mut buf = Vec::new();
+        write_csv_record(&mut buf, values().into_iter().map(csv_value));
+        assert_eq!(
+            String::from_utf8(buf).unwrap(),
+            "-1,1.5,\"a,b\",\"say \"\"hi\"\"\",\"line1\r\nline2\n\",,00abff\r\n"
+        );
+
+        let mut buf = Vec::new();
+        write_csv_record(&mut buf, vec![Cow::Borrowed("")].into_iter());
+        write_csv_record(&mut buf, vec![Cow::Borrowed("\r")].into_iter());
+        assert_eq!(buf, b"\r\n\"\r\"\r\n");
+    }
+
+    #[test]
+    fn test_write_json_object() -> Result<()> {
+        let columns = [
+            "integer", "real", "comma", "quote", "newline", "null", "blob",
+        ]
+        .iter()
+        .map(|c| c.to_string())
+        .collect::<Vec<_>>();
+        let mut buf = Vec::new();
+        write_json_object(&mut buf, &columns, &values())?;
+        assert_eq!(buf.last(), Some(&b'\n'));
+        assert_eq!(buf.iter().filter(|b| **b == b'\n').count(), 1);
+        let object: serde_json::Value = serde_json::from_slice(&buf)?;
+        assert_eq!(
+            object,
+            serde_json::json!({
+                "integer": -1,
+                "real": 1.5,
+                "comma": "a,b",
+                "quote": r#"say "hi""#,
+                "newline": "line1\r\nline2\n",
+                "null": null,
+                "blob": "00abff",
+            })
+        );
+
+        Ok(())
+    }
+}
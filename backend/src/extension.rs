@@ -0,0 +1,93 @@
+use crate::config::CONFIG;
+use async_graphql::{
+    extensions::{Extension, ExtensionContext, ExtensionFactory, NextValidation},
+    validators::IntGreaterThan,
+    InputObject, ServerError, SimpleObject, ValidationResult,
+};
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+const DEFAULT_MAX_COMPLEXITY: usize = 50000;
+const DEFAULT_MAX_DEPTH: usize = 15;
+
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq, Serialize, SimpleObject)]
+pub struct QueryLimit {
+    pub max_complexity: usize,
+    pub max_depth: usize,
+}
+
+impl Default for QueryLimit {
+    #[inline]
+    fn default() -> Self {
+        Self {
+            max_complexity: DEFAULT_MAX_COMPLEXITY,
+            max_depth: DEFAULT_MAX_DEPTH,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, InputObject)]
+pub struct QueryLimitInput {
+    #[graphql(validator(IntGreaterThan(value = "0")))]
+    pub max_complexity: usize,
+    #[graphql(validator(IntGreaterThan(value = "0")))]
+    pub max_depth: usize,
+}
+
+impl From<QueryLimitInput> for QueryLimit {
+    #[inline]
+    fn from(input: QueryLimitInput) -> Self {
+        Self {
+            max_complexity: input.max_complexity,
+            max_depth: input.max_depth,
+        }
+    }
+}
+
+// 每次请求时从配置里读取限制，修改后马上生效
+#[derive(Clone, Copy, Debug)]
+pub struct QueryLimiter;
+
+impl ExtensionFactory for QueryLimiter {
+    #[inline]
+    fn create(&self) -> Arc<dyn Extension> {
+        Arc::new(QueryLimiterExtension)
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+struct QueryLimiterExtension;
+
+#[async_trait]
+impl Extension for QueryLimiterExtension {
+    async fn validation(
+        &self,
+        ctx: &ExtensionContext<'_>,
+        next: NextValidation<'_>,
+    ) -> Result<ValidationResult, Vec<ServerError>> {
+        let result = next.run(ctx).await?;
+        let limit = CONFIG
+            .get()
+            .expect("failed to get CONFIG")
+            .read()
+            .await
+            .query_limit();
+        if result.depth > limit.max_depth {
+            log::warn!("query depth {} is too deep", result.depth);
+            return Err(vec![ServerError::new(format!(
+                "the query depth is {}, must be less than or equal to {}",
+                result.depth, limit.max_depth
+            ))]);
+        }
+        if result.complexity > limit.max_complexity {
+            log::warn!("query complexity {} is too complex", result.complexity);
+            return Err(vec![ServerError::new(format!(
+                "the query complexity is {}, must be less than or equal to {}, try to use smaller first or last",
+                result.complexity, limit.max_complexity
+            ))]);
+        }
+
+        Ok(result)
+    }
+}
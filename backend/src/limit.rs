@@ -0,0 +1,311 @@
+use crate::config::User;
+use ahash::AHashMap;
+use async_graphql::{validators::IntGreaterThan, InputObject, SimpleObject};
+use axum::http::{
+    header::{HeaderMap, HeaderValue, RETRY_AFTER},
+    StatusCode,
+};
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use std::{
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+const DEFAULT_REQUESTS_PER_MINUTE: u32 = 120;
+const DEFAULT_CONCURRENT_QUERIES: u32 = 5;
+// 超过这个时间没有请求的用户的令牌已经恢复满，可以清理
+const EXPIRE: Duration = Duration::from_secs(60);
+
+pub static LIMITER: Lazy<Limiter> = Lazy::new(Limiter::default);
+
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq, Serialize, SimpleObject)]
+pub struct RateLimit {
+    pub requests_per_minute: u32,
+    pub concurrent_queries: u32,
+}
+
+impl Default for RateLimit {
+    #[inline]
+    fn default() -> Self {
+        Self {
+            requests_per_minute: DEFAULT_REQUESTS_PER_MINUTE,
+            concurrent_queries: DEFAULT_CONCURRENT_QUERIES,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, InputObject)]
+pub struct RateLimitInput {
+    #[graphql(validator(IntGreaterThan(value = "0")))]
+    pub requests_per_minute: u32,
+    #[graphql(validator(IntGreaterThan(value = "0")))]
+    pub concurrent_queries: u32,
+}
+
+impl From<RateLimitInput> for RateLimit {
+    #[inline]
+    fn from(input: RateLimitInput) -> Self {
+        Self {
+            requests_per_minute: input.requests_per_minute,
+            concurrent_queries: input.concurrent_queries,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, thiserror::Error)]
+#[error("too many requests, retry after {retry_after} seconds")]
+pub struct Limited {
+    pub retry_after: u64,
+}
+
+impl Limited {
+    pub fn response(&self) -> (StatusCode, HeaderMap, String) {
+        let mut headers = HeaderMap::with_capacity(1);
+        let _ = headers.insert(RETRY_AFTER, HeaderValue::from(self.retry_after));
+        (StatusCode::TOO_MANY_REQUESTS, headers, self.to_string())
+    }
+}
+
+// 令牌桶，每分钟恢复`requests_per_minute`个
+#[derive(Clone, Copy, Debug)]
+struct State {
+    limit: RateLimit,
+    tokens: f64,
+    updated: Instant,
+    // 最后一次请求的时间，refill()不会更新
+    last_request: Instant,
+    running: u32,
+}
+
+impl State {
+    #[inline]
+    fn new(limit: RateLimit) -> Self {
+        let now = Instant::now();
+        Self {
+            limit,
+            tokens: limit.requests_per_minute as f64,
+            updated: now,
+            last_request: now,
+            running: 0,
+        }
+    }
+
+    #[inline]
+    fn expired(&self) -> bool {
+        self.running == 0 && self.last_request.elapsed() >= EXPIRE
+    }
+
+    #[inline]
+    fn refill(&mut self, limit: RateLimit) {
+        let now = Instant::now();
+        let capacity = limit.requests_per_minute as f64;
+        let elapsed = now.duration_since(self.updated).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * capacity / 60.0).min(capacity);
+        self.updated = now;
+        self.limit = limit;
+    }
+
+    // 还需要等待多少秒才有令牌
+    #[inline]
+    fn retry_after(&self) -> u64 {
+        if self.tokens >= 1.0 {
+            0
+        } else {
+            let per_second = self.limit.requests_per_minute as f64 / 60.0;
+            ((1.0 - self.tokens) / per_second).ceil() as u64
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, SimpleObject)]
+pub struct LiverRateLimit {
+    pub liver_uid: i64,
+    pub limit: RateLimit,
+}
+
+// `status`为最近一分钟内有请求的用户的状态
+#[derive(Clone, Debug, SimpleObject)]
+pub struct RateLimits {
+    pub default_limit: RateLimit,
+    pub liver_limits: Vec<LiverRateLimit>,
+    pub status: Vec<LimitStatus>,
+}
+
+#[derive(Clone, Debug, SimpleObject)]
+pub struct LimitStatus {
+    pub liver_uid: Option<i64>,
+    pub limit: RateLimit,
+    pub available_requests: i64,
+    pub running_queries: u32,
+    pub retry_after: u64,
+}
+
+#[derive(Debug, Default)]
+pub struct Limiter {
+    states: Mutex<AHashMap<User, State>>,
+}
+
+impl Limiter {
+    // `limit`为None时不限制，返回的Permit在drop时释放并发数
+    pub fn acquire(&'static self, user: User, limit: Option<RateLimit>) -> Result<Permit, Limited> {
+        let limit = match limit {
+            Some(limit) => limit,
+            None => {
+                return Ok(Permit {
+                    user: None,
+                    limiter: self,
+                })
+            }
+        };
+        let mut states = self.states.lock().expect("failed to lock Limiter");
+        // 清理已经恢复满而且没有在运行的状态
+        states.retain(|_, s| !s.expired());
+        let state = states.entry(user).or_insert_with(|| State::new(limit));
+        state.refill(limit);
+        state.last_request = Instant::now();
+        if state.running >= limit.concurrent_queries {
+            log::warn!("{:?} has too many concurrent queries", user);
+            return Err(Limited { retry_after: 1 });
+        }
+        if state.tokens < 1.0 {
+            log::warn!("{:?} is rate limited", user);
+            return Err(Limited {
+                retry_after: state.retry_after(),
+            });
+        }
+        state.tokens -= 1.0;
+        state.running += 1;
+
+        Ok(Permit {
+            user: Some(user),
+            limiter: self,
+        })
+    }
+
+    #[inline]
+    fn release(&self, user: User) {
+        let mut states = self.states.lock().expect("failed to lock Limiter");
+        if let Some(state) = states.get_mut(&user) {
+            state.running = state.running.saturating_sub(1);
+        }
+    }
+
+    pub fn status(&self) -> Vec<LimitStatus> {
+        let mut states = self.states.lock().expect("failed to lock Limiter");
+        states.retain(|_, s| !s.expired());
+        let mut status = states
+            .iter_mut()
+            .map(|(user, state)| {
+                state.refill(state.limit);
+                LimitStatus {
+                    liver_uid: user.liver_uid(),
+                    limit: state.limit,
+                    available_requests: state.tokens.floor() as i64,
+                    running_queries: state.running,
+                    retry_after: state.retry_after(),
+                }
+            })
+            .collect::<Vec<_>>();
+        status.sort_unstable_by_key(|s| s.liver_uid);
+
+        status
+    }
+}
+
+#[derive(Debug)]
+pub struct Permit {
+    user: Option<User>,
+    limiter: &'static Limiter,
+}
+
+impl Drop for Permit {
+    #[inline]
+    fn drop(&mut self) {
+        if let Some(user) = self.user {
+            self.limiter.release(user);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn limiter() -> &'static Limiter {
+        Box::leak(Box::new(Limiter::default()))
+    }
+
+    #[test]
+    fn test_refill() {
+        let limit = RateLimit {
+            requests_per_minute: 60,
+            concurrent_queries: 1,
+        };
+        let mut state = State::new(limit);
+        state.tokens = 0.0;
+        assert_eq!(state.retry_after(), 1);
+        state.updated -= Duration::from_secs(30);
+        state.refill(limit);
+        assert!((state.tokens - 30.0).abs() < 0.1);
+        assert_eq!(state.retry_after(), 0);
+        // 不会超过每分钟的请求数
+        state.updated -= Duration::from_secs(120);
+        state.refill(limit);
+        assert!((state.tokens - 60.0).abs() < f64::EPSILON);
+    }
+
+    #[test]
+    fn test_rate_limited() {
+        let limiter = limiter();
+        let user = User::Liver(1);
+        let limit = Some(RateLimit {
+            requests_per_minute: 2,
+            concurrent_queries: 5,
+        });
+        drop(limiter.acquire(user, limit).unwrap());
+        drop(limiter.acquire(user, limit).unwrap());
+        let limited = limiter.acquire(user, limit).unwrap_err();
+        assert_eq!(limited.retry_after, 30);
+        let (status, headers, _) = limited.response();
+        assert_eq!(status, StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(headers[RETRY_AFTER], "30");
+        // 其他用户不受影响，没有限制时不计数
+        assert!(limiter.acquire(User::Liver(2), limit).is_ok());
+        assert!(limiter.acquire(User::Admin, None).is_ok());
+    }
+
+    #[test]
+    fn test_concurrent_queries() {
+        let limiter = limiter();
+        let user = User::Liver(1);
+        let limit = Some(RateLimit {
+            requests_per_minute: 60,
+            concurrent_queries: 2,
+        });
+        let first = limiter.acquire(user, limit).unwrap();
+        let _second = limiter.acquire(user, limit).unwrap();
+        assert_eq!(limiter.acquire(user, limit).unwrap_err().retry_after, 1);
+        drop(first);
+        assert!(limiter.acquire(user, limit).is_ok());
+        assert_eq!(limiter.status()[0].running_queries, 1);
+    }
+
+    #[test]
+    fn test_expire() {
+        let limiter = limiter();
+        let limit = Some(RateLimit::default());
+        drop(limiter.acquire(User::Liver(1), limit).unwrap());
+        let _running = limiter.acquire(User::Liver(2), limit).unwrap();
+        for state in limiter.states.lock().unwrap().values_mut() {
+            state.last_request -= EXPIRE;
+        }
+        // 查询状态不会推迟清理
+        assert_eq!(limiter.status().len(), 1);
+        drop(limiter.acquire(User::Liver(3), limit).unwrap());
+        let states = limiter.states.lock().unwrap();
+        assert!(!states.contains_key(&User::Liver(1)));
+        assert!(states.contains_key(&User::Liver(2)));
+        assert!(states.contains_key(&User::Liver(3)));
+    }
+}
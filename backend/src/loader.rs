@@ -0,0 +1,168 @@
+use crate::{pool::RusqliteConnectionManager, row::FromRow, sql::*};
+use acfunlivedata_common::data::{LiveId, LiverInfo, Summary, Title};
+use anyhow::Error;
+use async_graphql::dataloader::Loader;
+use async_trait::async_trait;
+use bb8::Pool;
+use rusqlite::ToSql;
+use std::{collections::HashMap, sync::Arc};
+
+// SQLite的参数数量有限制
+const MAX_BATCH_SIZE: usize = 500;
+
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub struct TitleKey(pub LiveId);
+
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub struct LiverInfoKey(pub LiveId);
+
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub struct SummaryKey(pub LiveId);
+
+pub trait LiveIdRow {
+    fn live_id(&self) -> &LiveId;
+}
+
+impl LiveIdRow for Title {
+    #[inline]
+    fn live_id(&self) -> &LiveId {
+        &self.live_id
+    }
+}
+
+impl LiveIdRow for LiverInfo {
+    #[inline]
+    fn live_id(&self) -> &LiveId {
+        &self.live_id
+    }
+}
+
+impl LiveIdRow for Summary {
+    #[inline]
+    fn live_id(&self) -> &LiveId {
+        &self.live_id
+    }
+}
+
+// 按live_id批量查询某个主播数据库里的数据，每个请求新建一个
+#[derive(Clone, Debug)]
+pub struct LiveLoader {
+    pool: Pool<RusqliteConnectionManager>,
+}
+
+impl LiveLoader {
+    #[inline]
+    pub fn new(pool: Pool<RusqliteConnectionManager>) -> Self {
+        Self { pool }
+    }
+
+    // 按save_time升序返回
+    async fn load_rows<T>(
+        &self,
+        select: &'static str,
+        live_ids: Vec<LiveId>,
+    ) -> Result<Vec<T>, Arc<Error>>
+    where
+        T: FromRow + Send + 'static,
+    {
+        let pool = self.pool.clone();
+        tokio::task::spawn_blocking(move || {
+            let conn = futures::executor::block_on(pool.get())?;
+            let mut list = Vec::new();
+            for chunk in live_ids.chunks(MAX_BATCH_SIZE) {
+                let mut sql = select.to_string();
+                sql += WHERE;
+                sql += LIVE_ID_IN;
+                sql += &vec!["?"; chunk.len()].join(", ");
+                sql += RIGHT_PARENTHESES;
+                sql += ORDER_SAVE_TIME_ASC;
+                sql += SEMICOLON;
+                let params = chunk.iter().map(|id| id as &dyn ToSql).collect::<Vec<_>>();
+                let mut stmt = conn.prepare_cached(&sql)?;
+                let rows = stmt.query_map(params.as_slice(), T::from_row)?;
+                for row in rows {
+                    list.push(row?);
+                }
+            }
+
+            Ok::<_, Error>(list)
+        })
+        .await
+        .map_err(|e| Arc::new(Error::from(e)))?
+        .map_err(Arc::new)
+    }
+}
+
+#[async_trait]
+impl Loader<TitleKey> for LiveLoader {
+    type Value = Vec<Title>;
+
+    type Error = Arc<Error>;
+
+    async fn load(&self, keys: &[TitleKey]) -> Result<HashMap<TitleKey, Self::Value>, Self::Error> {
+        let titles: Vec<Title> = self
+            .load_rows(SELECT_TITLE, keys.iter().map(|k| k.0.clone()).collect())
+            .await?;
+        let mut map: HashMap<_, Vec<Title>> =
+            keys.iter().map(|k| (k.clone(), Vec::new())).collect();
+        for title in titles {
+            if let Some(list) = map.get_mut(&TitleKey(title.live_id.clone())) {
+                list.push(title);
+            }
+        }
+
+        Ok(map)
+    }
+}
+
+#[async_trait]
+impl Loader<LiverInfoKey> for LiveLoader {
+    type Value = LiverInfo;
+
+    type Error = Arc<Error>;
+
+    // 同一场直播有多条的话取最后一条
+    async fn load(
+        &self,
+        keys: &[LiverInfoKey],
+    ) -> Result<HashMap<LiverInfoKey, Self::Value>, Self::Error> {
+        Ok(last_by_live_id(
+            self.load_rows(
+                SELECT_LIVER_INFO,
+                keys.iter().map(|k| k.0.clone()).collect(),
+            )
+            .await?,
+            LiverInfoKey,
+        ))
+    }
+}
+
+#[async_trait]
+impl Loader<SummaryKey> for LiveLoader {
+    type Value = Summary;
+
+    type Error = Arc<Error>;
+
+    async fn load(
+        &self,
+        keys: &[SummaryKey],
+    ) -> Result<HashMap<SummaryKey, Self::Value>, Self::Error> {
+        Ok(last_by_live_id(
+            self.load_rows(SELECT_SUMMARY, keys.iter().map(|k| k.0.clone()).collect())
+                .await?,
+            SummaryKey,
+        ))
+    }
+}
+
+#[inline]
+fn last_by_live_id<K, T, F>(list: Vec<T>, key: F) -> HashMap<K, T>
+where
+    K: Eq + std::hash::Hash,
+    T: LiveIdRow,
+    F: Fn(LiveId) -> K,
+{
+    list.into_iter()
+        .map(|row| (key(row.live_id().clone()), row))
+        .collect()
+}
@@ -3,12 +3,22 @@
 mod auth;
 mod config;
 mod download;
+mod export;
+mod extension;
+mod limit;
+mod loader;
 mod model;
+mod mutation;
+mod object;
+mod page;
 mod pool;
+mod row;
+mod scope;
 mod server;
 mod socket;
 mod sql;
 mod sqlite;
+mod stat;
 
 use acfunlivedata_common::{
     config::Config as CommonConfig,
@@ -59,10 +69,12 @@ fn main() -> Result<()> {
             )
             .await
             .expect("failed to load config");
+            if config.hash_plaintext_tokens() {
+                config.save_config().await.expect("failed to save config");
+            }
             if !config.contains_admin_token() {
-                let token = config::generate_token();
+                let token = config.set_admin_token();
                 println!("admin token:\n{}", token);
-                config.set_admin_token(token);
                 config.save_config().await.expect("failed to save config");
             }
             if config::CONFIG.set(RwLock::new(config)).is_err() {
@@ -72,6 +84,9 @@ fn main() -> Result<()> {
             create_dir(&*download::TEMP_DIRECTORY)
                 .await
                 .expect("failed to create temp directory");
+            download::clean_temp_directory()
+                .await
+                .expect("failed to clean temp directory");
 
             tokio::select! {
                 _ = socket::message(backend_password) => {}
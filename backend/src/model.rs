@@ -1,18 +1,28 @@
 use crate::{
-    config::{TokenInfo, User, CONFIG},
+    config::{TokenInfo, TokenMeta, User, CONFIG},
+    extension::QueryLimit,
+    limit::{RateLimits, LIMITER},
+    loader::LiveLoader,
+    object::{LiveInfoNode, LiveNode, UserActivity},
+    page::{self, LiveConnection, OrderBy, Page},
     pool::Connection,
+    row::FromRow,
+    scope::{check_table, check_unscoped, Table, TableGuard},
+    socket::recording_live,
     sql::*,
     sqlite::connect,
+    stat::{self, Bucket, Timeline, TimelineKind, UserCommentStat, UserGiftStat},
 };
 use acfunlivedata_common::{data::*, database::*};
 use ahash::AHashMap;
-use anyhow::{bail, Result};
+use anyhow::{anyhow, bail, Result};
 use async_graphql::{
+    dataloader::DataLoader,
     validators::{InputValueValidator, IntGreaterThan},
     Context, Object, Value,
 };
 use cached::proc_macro::cached;
-use rusqlite::ToSql;
+use rusqlite::{OptionalExtension, ToSql};
 use std::{iter, sync::Arc};
 
 #[derive(Clone, Copy, Debug)]
@@ -58,8 +68,7 @@ macro_rules! sql_and_params {
                 params.push(arg);
             }
         )*
-        sql += SEMICOLON;
-        (sql, params)
+        (sql, params, where_or_and)
     }}
 }
 
@@ -157,7 +166,7 @@ impl InputValueValidator for ListIntGreaterThan {
 
 #[Object]
 impl QueryRoot {
-    #[graphql(visible = false)]
+    #[graphql(visible = false, deprecation = "use the mutation addLiver instead")]
     async fn add_liver(
         &self,
         ctx: &Context<'_>,
@@ -173,7 +182,7 @@ impl QueryRoot {
         Ok(token)
     }
 
-    #[graphql(visible = false)]
+    #[graphql(visible = false, deprecation = "use the mutation deleteLiver instead")]
     async fn delete_liver(
         &self,
         ctx: &Context<'_>,
@@ -197,7 +206,73 @@ impl QueryRoot {
         }
     }
 
+    // 正在直播时返回live_id，受限token需要有LiveInfo的权限
+    #[graphql(guard(TableGuard(table = "Table::LiveInfo")))]
+    async fn recording_live(
+        &self,
+        ctx: &Context<'_>,
+        #[graphql(validator(IntGreaterThan(value = "0")), visible = false)] liver_uid: Option<i64>,
+    ) -> Result<Option<String>> {
+        let liver_uid = match (ctx.data_unchecked::<User>(), liver_uid) {
+            (User::Admin, None) => bail!("admin token need liver_uid"),
+            (User::Admin, Some(liver_uid)) => liver_uid,
+            (User::Liver(liver_uid), None) => *liver_uid,
+            (User::Liver(_), Some(_)) => bail!("liver token don't need liver_uid"),
+        };
+
+        recording_live(liver_uid).await
+    }
+
+    // admin可以查看所有token，主播只能查看自己的token
+    async fn tokens(
+        &self,
+        ctx: &Context<'_>,
+        #[graphql(validator(IntGreaterThan(value = "0")), visible = false)] liver_uid: Option<i64>,
+    ) -> Result<Vec<TokenMeta>> {
+        check_unscoped(ctx)?;
+        let liver_uid = match (ctx.data_unchecked::<User>(), liver_uid) {
+            (User::Admin, liver_uid) => liver_uid,
+            (User::Liver(liver_uid), None) => Some(*liver_uid),
+            (User::Liver(_), Some(_)) => bail!("liver token don't need liver_uid"),
+        };
+        let config = CONFIG.get().expect("failed to get CONFIG").read().await;
+
+        Ok(config.tokens(liver_uid))
+    }
+
+    #[graphql(visible = false)]
+    async fn query_limit(&self, ctx: &Context<'_>) -> Result<QueryLimit> {
+        if !ctx.data_unchecked::<User>().is_admin() {
+            bail!("the admin authorization is needed");
+        }
+
+        Ok(CONFIG
+            .get()
+            .expect("failed to get CONFIG")
+            .read()
+            .await
+            .query_limit())
+    }
+
     #[graphql(visible = false)]
+    async fn rate_limits(&self, ctx: &Context<'_>) -> Result<RateLimits> {
+        if !ctx.data_unchecked::<User>().is_admin() {
+            bail!("the admin authorization is needed");
+        }
+        let config = CONFIG.get().expect("failed to get CONFIG").read().await;
+
+        Ok(RateLimits {
+            default_limit: config.default_rate_limit(),
+            liver_limits: config.liver_rate_limits(),
+            status: LIMITER.status(),
+        })
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    #[graphql(
+        visible = false,
+        complexity = "page::complexity(first, last, child_complexity)"
+    )]
     async fn live(
         &self,
         ctx: &Context<'_>,
@@ -205,15 +280,21 @@ impl QueryRoot {
         #[graphql(validator(ListIntGreaterThan(value = "0")))] liver_uid: Option<Vec<i64>>,
         #[graphql(validator(IntGreaterThan(value = "-1")))] start: Option<i64>,
         #[graphql(validator(IntGreaterThan(value = "-1")))] end: Option<i64>,
-    ) -> Result<Vec<Live>> {
+        after: Option<String>,
+        before: Option<String>,
+        #[graphql(validator(IntGreaterThan(value = "-1")))] first: Option<i32>,
+        #[graphql(validator(IntGreaterThan(value = "-1")))] last: Option<i32>,
+        order_by: Option<OrderBy>,
+    ) -> Result<LiveConnection<LiveNode>> {
         if !ctx.data_unchecked::<User>().is_admin() {
             bail!("the admin authorization is needed");
         }
         let pool = connect(ACFUN_LIVE_DATABASE.clone()).await?;
+        let page = Page::new(ctx, after, before, first, last, order_by)?;
 
         tokio::task::spawn_blocking(move || {
             compare_start_end!(start, end);
-            let (sql, params) = sql_and_params!(
+            let (sql, params, where_or_and) = sql_and_params!(
                 SELECT_LIVE;
                 (live_id, LIVE_ID),
                 (liver_uid, LIVER_UID);
@@ -222,37 +303,9 @@ impl QueryRoot {
             );
 
             let conn = futures::executor::block_on(pool.get())?;
-            let mut stmt = conn.prepare_cached(&sql)?;
-            let lives = stmt
-                .query_map(params.as_slice(), |r| {
-                    let live_type = match r.get::<_, Option<i32>>(6)? {
-                        Some(id) => Some(LiveType {
-                            id,
-                            name: r.get(7)?,
-                            category_id: r.get(8)?,
-                            category_name: r.get(9)?,
-                        }),
-                        None => None,
-                    };
-                    Ok(Live {
-                        live_id: Arc::new(r.get(0)?),
-                        liver_uid: r.get(1)?,
-                        nickname: r.get(2)?,
-                        stream_name: r.get(3)?,
-                        start_time: r.get(4)?,
-                        title: r.get(5)?,
-                        live_type,
-                        portrait: r.get(10)?,
-                        panoramic: r.get(11)?,
-                        disable_danmaku_show: r.get(12)?,
-                        duration: r.get(13)?,
-                        like_count: r.get(14)?,
-                        watch_count: r.get(15)?,
-                    })
-                })?
-                .collect::<rusqlite::Result<Vec<Live>>>()?;
-
-            Ok(lives)
+            page.query_with(&conn, sql, params, where_or_and, Live::KEYS, |r| {
+                Ok(LiveNode::new(Live::from_row(r)?))
+            })
         })
         .await?
     }
@@ -272,6 +325,11 @@ impl QueryRoot {
         cache_gift_info(gift_id, all_history).await
     }
 
+    #[allow(clippy::too_many_arguments)]
+    #[graphql(
+        guard(TableGuard(table = "Table::LiveInfo")),
+        complexity = "page::complexity(first, last, child_complexity)"
+    )]
     async fn live_info(
         &self,
         ctx: &Context<'_>,
@@ -279,12 +337,20 @@ impl QueryRoot {
         #[graphql(validator(IntGreaterThan(value = "-1")))] start: Option<i64>,
         #[graphql(validator(IntGreaterThan(value = "-1")))] end: Option<i64>,
         #[graphql(validator(IntGreaterThan(value = "0")), visible = false)] liver_uid: Option<i64>,
-    ) -> Result<Vec<LiveInfo>> {
+        after: Option<String>,
+        before: Option<String>,
+        #[graphql(validator(IntGreaterThan(value = "-1")))] first: Option<i32>,
+        #[graphql(validator(IntGreaterThan(value = "-1")))] last: Option<i32>,
+        order_by: Option<OrderBy>,
+    ) -> Result<LiveConnection<LiveInfoNode>> {
         let pool = get_pool!(ctx, liver_uid);
+        let page = Page::new(ctx, after, before, first, last, order_by)?;
+        // 这一页的所有直播共用一个DataLoader
+        let loader = Arc::new(DataLoader::new(LiveLoader::new(pool.clone())));
 
         tokio::task::spawn_blocking(move || {
             compare_start_end!(start, end);
-            let (sql, params) = sql_and_params!(
+            let (sql, params, where_or_and) = sql_and_params!(
                 SELECT_LIVE_INFO;
                 (live_id, LIVE_ID);
                 (start, START_TIME_START),
@@ -292,60 +358,22 @@ impl QueryRoot {
             );
 
             let conn = futures::executor::block_on(pool.get())?;
-            let mut stmt = conn.prepare_cached(&sql)?;
-            let list = stmt
-                .query_map(params.as_slice(), |r| {
-                    let live_type = match r.get::<_, Option<i32>>(5)? {
-                        Some(id) => Some(LiveType {
-                            id,
-                            name: r.get(6)?,
-                            category_id: r.get(7)?,
-                            category_name: r.get(8)?,
-                        }),
-                        None => None,
-                    };
-                    let live_id: Arc<String> = Arc::new(r.get(0)?);
-                    let mut where_live_id = WHERE.to_string();
-                    where_live_id.push_str(LIVE_ID);
-                    where_live_id.push_str(SEMICOLON);
-                    let params = &[(&live_id as &dyn ToSql)];
-
-                    let mut sql = SELECT_TITLE.to_string();
-                    sql.push_str(&where_live_id);
-                    let titles = title(&conn, &sql, params)?;
-                    sql.clear();
-                    sql.push_str(SELECT_LIVER_INFO);
-                    sql.push_str(&where_live_id);
-                    let mut liver_info = liver_info(&conn, &sql, params)?;
-                    sql.clear();
-                    sql.push_str(SELECT_SUMMARY);
-                    sql.push_str(&where_live_id);
-                    let mut summaries = summary(&conn, &sql, params)?;
-
-                    Ok(LiveInfo {
-                        live_id,
-                        liver_uid: r.get(1)?,
-                        liver_info: liver_info.pop(),
-                        stream_name: r.get(2)?,
-                        start_time: r.get(3)?,
-                        title: Some(titles),
-                        cover: r.get(4)?,
-                        live_type,
-                        has_fans_club: r.get(9)?,
-                        portrait: r.get(10)?,
-                        panoramic: r.get(11)?,
-                        disable_danmaku_show: r.get(12)?,
-                        paid_show_user_buy_status: r.get(13)?,
-                        summary: summaries.pop(),
-                    })
-                })?
-                .collect::<rusqlite::Result<Vec<LiveInfo>>>()?;
-
-            Ok(list)
+            page.query_with(&conn, sql, params, where_or_and, LiveInfo::KEYS, |r| {
+                Ok(LiveInfoNode::new(
+                    LiveInfo::from_row(r)?,
+                    loader.clone(),
+                    pool.clone(),
+                ))
+            })
         })
         .await?
     }
 
+    #[allow(clippy::too_many_arguments)]
+    #[graphql(
+        guard(TableGuard(table = "Table::Title")),
+        complexity = "page::complexity(first, last, child_complexity)"
+    )]
     async fn title(
         &self,
         ctx: &Context<'_>,
@@ -353,12 +381,18 @@ impl QueryRoot {
         #[graphql(validator(IntGreaterThan(value = "-1")))] start: Option<i64>,
         #[graphql(validator(IntGreaterThan(value = "-1")))] end: Option<i64>,
         #[graphql(validator(IntGreaterThan(value = "0")), visible = false)] liver_uid: Option<i64>,
-    ) -> Result<Vec<Title>> {
+        after: Option<String>,
+        before: Option<String>,
+        #[graphql(validator(IntGreaterThan(value = "-1")))] first: Option<i32>,
+        #[graphql(validator(IntGreaterThan(value = "-1")))] last: Option<i32>,
+        order_by: Option<OrderBy>,
+    ) -> Result<LiveConnection<Title>> {
         let pool = get_pool!(ctx, liver_uid);
+        let page = Page::new(ctx, after, before, first, last, order_by)?;
 
         tokio::task::spawn_blocking(move || {
             compare_start_end!(start, end);
-            let (sql, params) = sql_and_params!(
+            let (sql, params, where_or_and) = sql_and_params!(
                 SELECT_TITLE;
                 (live_id, LIVE_ID);
                 (start, SAVE_TIME_START),
@@ -366,11 +400,16 @@ impl QueryRoot {
             );
 
             let conn = futures::executor::block_on(pool.get())?;
-            Ok(title(&conn, &sql, &params)?)
+            page.query(&conn, sql, params, where_or_and)
         })
         .await?
     }
 
+    #[allow(clippy::too_many_arguments)]
+    #[graphql(
+        guard(TableGuard(table = "Table::LiverInfo")),
+        complexity = "page::complexity(first, last, child_complexity)"
+    )]
     async fn liver_info(
         &self,
         ctx: &Context<'_>,
@@ -378,12 +417,18 @@ impl QueryRoot {
         #[graphql(validator(IntGreaterThan(value = "-1")))] start: Option<i64>,
         #[graphql(validator(IntGreaterThan(value = "-1")))] end: Option<i64>,
         #[graphql(validator(IntGreaterThan(value = "0")), visible = false)] liver_uid: Option<i64>,
-    ) -> Result<Vec<LiverInfo>> {
+        after: Option<String>,
+        before: Option<String>,
+        #[graphql(validator(IntGreaterThan(value = "-1")))] first: Option<i32>,
+        #[graphql(validator(IntGreaterThan(value = "-1")))] last: Option<i32>,
+        order_by: Option<OrderBy>,
+    ) -> Result<LiveConnection<LiverInfo>> {
         let pool = get_pool!(ctx, liver_uid);
+        let page = Page::new(ctx, after, before, first, last, order_by)?;
 
         tokio::task::spawn_blocking(move || {
             compare_start_end!(start, end);
-            let (sql, params) = sql_and_params!(
+            let (sql, params, where_or_and) = sql_and_params!(
                 SELECT_LIVER_INFO;
                 (live_id, LIVE_ID);
                 (start, SAVE_TIME_START),
@@ -391,11 +436,16 @@ impl QueryRoot {
             );
 
             let conn = futures::executor::block_on(pool.get())?;
-            Ok(liver_info(&conn, &sql, &params)?)
+            page.query(&conn, sql, params, where_or_and)
         })
         .await?
     }
 
+    #[allow(clippy::too_many_arguments)]
+    #[graphql(
+        guard(TableGuard(table = "Table::Summary")),
+        complexity = "page::complexity(first, last, child_complexity)"
+    )]
     async fn summary(
         &self,
         ctx: &Context<'_>,
@@ -403,12 +453,18 @@ impl QueryRoot {
         #[graphql(validator(IntGreaterThan(value = "-1")))] start: Option<i64>,
         #[graphql(validator(IntGreaterThan(value = "-1")))] end: Option<i64>,
         #[graphql(validator(IntGreaterThan(value = "0")), visible = false)] liver_uid: Option<i64>,
-    ) -> Result<Vec<Summary>> {
+        after: Option<String>,
+        before: Option<String>,
+        #[graphql(validator(IntGreaterThan(value = "-1")))] first: Option<i32>,
+        #[graphql(validator(IntGreaterThan(value = "-1")))] last: Option<i32>,
+        order_by: Option<OrderBy>,
+    ) -> Result<LiveConnection<Summary>> {
         let pool = get_pool!(ctx, liver_uid);
+        let page = Page::new(ctx, after, before, first, last, order_by)?;
 
         tokio::task::spawn_blocking(move || {
             compare_start_end!(start, end);
-            let (sql, params) = sql_and_params!(
+            let (sql, params, where_or_and) = sql_and_params!(
                 SELECT_SUMMARY;
                 (live_id, LIVE_ID);
                 (start, SAVE_TIME_START),
@@ -416,12 +472,16 @@ impl QueryRoot {
             );
 
             let conn = futures::executor::block_on(pool.get())?;
-            Ok(summary(&conn, &sql, &params)?)
+            page.query(&conn, sql, params, where_or_and)
         })
         .await?
     }
 
     #[allow(clippy::too_many_arguments)]
+    #[graphql(
+        guard(TableGuard(table = "Table::Comment")),
+        complexity = "page::complexity(first, last, child_complexity)"
+    )]
     async fn comment(
         &self,
         ctx: &Context<'_>,
@@ -430,12 +490,18 @@ impl QueryRoot {
         #[graphql(validator(IntGreaterThan(value = "-1")))] start: Option<i64>,
         #[graphql(validator(IntGreaterThan(value = "-1")))] end: Option<i64>,
         #[graphql(validator(IntGreaterThan(value = "0")), visible = false)] liver_uid: Option<i64>,
-    ) -> Result<Vec<Comment>> {
+        after: Option<String>,
+        before: Option<String>,
+        #[graphql(validator(IntGreaterThan(value = "-1")))] first: Option<i32>,
+        #[graphql(validator(IntGreaterThan(value = "-1")))] last: Option<i32>,
+        order_by: Option<OrderBy>,
+    ) -> Result<LiveConnection<Comment>> {
         let pool = get_pool!(ctx, liver_uid);
+        let page = Page::new(ctx, after, before, first, last, order_by)?;
 
         tokio::task::spawn_blocking(move || {
             compare_start_end!(start, end);
-            let (sql, params) = sql_and_params!(
+            let (sql, params, where_or_and) = sql_and_params!(
                 SELECT_COMMENT;
                 (live_id, LIVE_ID),
                 (user_id, USER_ID);
@@ -444,89 +510,51 @@ impl QueryRoot {
             );
 
             let conn = futures::executor::block_on(pool.get())?;
-            let mut stmt = conn.prepare_cached(&sql)?;
-            let comments = stmt
-                .query_map(params.as_slice(), |r| {
-                    let medal = match r.get::<_, Option<i64>>(5)? {
-                        Some(uper_uid) => Some(MedalInfo {
-                            uper_uid,
-                            name: r.get(6)?,
-                            level: r.get(7)?,
-                        }),
-                        None => None,
-                    };
-                    let user_info = match r.get::<_, Option<i64>>(2)? {
-                        Some(user_id) => Some(UserInfo {
-                            user_id,
-                            nickname: r.get(3)?,
-                            avatar: r.get(4)?,
-                            medal,
-                            manager: r.get(8)?,
-                        }),
-                        None => None,
-                    };
-                    Ok(Comment {
-                        live_id: Arc::new(r.get(0)?),
-                        send_time: r.get(1)?,
-                        user_info,
-                        content: r.get(9)?,
-                    })
-                })?
-                .collect::<rusqlite::Result<Vec<Comment>>>()?;
-
-            Ok(comments)
+            page.query(&conn, sql, params, where_or_and)
         })
         .await?
     }
 
-    async fn follow(
+    #[allow(clippy::too_many_arguments)]
+    #[graphql(
+        guard(TableGuard(table = "Table::Comment")),
+        complexity = "stat::complexity(limit, child_complexity)"
+    )]
+    async fn user_comment_stat(
         &self,
         ctx: &Context<'_>,
         #[graphql(validator(ListStringMinLength(length = "1")))] live_id: Option<Vec<String>>,
+        #[graphql(validator(ListIntGreaterThan(value = "0")))] user_id: Option<Vec<i64>>,
         #[graphql(validator(IntGreaterThan(value = "-1")))] start: Option<i64>,
         #[graphql(validator(IntGreaterThan(value = "-1")))] end: Option<i64>,
         #[graphql(validator(IntGreaterThan(value = "0")), visible = false)] liver_uid: Option<i64>,
-    ) -> Result<Vec<Follow>> {
+        #[graphql(validator(IntGreaterThan(value = "0")))] limit: Option<i32>,
+    ) -> Result<Vec<UserCommentStat>> {
         let pool = get_pool!(ctx, liver_uid);
+        let limit = stat::limit(limit)?;
 
         tokio::task::spawn_blocking(move || {
             compare_start_end!(start, end);
-            let (sql, params) = sql_and_params!(
-                SELECT_FOLLOW;
-                (live_id, LIVE_ID);
+            let (mut sql, mut params, mut where_or_and) = sql_and_params!(
+                SELECT_USER_COMMENT_STAT;
+                (live_id, LIVE_ID),
+                (user_id, USER_ID);
                 (start, SEND_TIME_START),
                 (end, SEND_TIME_END)
             );
+            sql += where_or_and.next().unwrap();
+            sql += USER_ID_NOT_NULL;
+            sql += GROUP_BY_USER_ID;
+            sql += ORDER_COMMENT_COUNT_DESC;
+            sql += LIMIT;
+            sql += SEMICOLON;
+            params.push(&limit);
 
             let conn = futures::executor::block_on(pool.get())?;
             let mut stmt = conn.prepare_cached(&sql)?;
             let list = stmt
-                .query_map(params.as_slice(), |r| {
-                    let medal = match r.get::<_, Option<i64>>(5)? {
-                        Some(uper_uid) => Some(MedalInfo {
-                            uper_uid,
-                            name: r.get(6)?,
-                            level: r.get(7)?,
-                        }),
-                        None => None,
-                    };
-                    let user_info = match r.get::<_, Option<i64>>(2)? {
-                        Some(user_id) => Some(UserInfo {
-                            user_id,
-                            nickname: r.get(3)?,
-                            avatar: r.get(4)?,
-                            medal,
-                            manager: r.get(8)?,
-                        }),
-                        None => None,
-                    };
-                    Ok(Follow {
-                        live_id: Arc::new(r.get(0)?),
-                        send_time: r.get(1)?,
-                        user_info,
-                    })
-                })?
-                .collect::<rusqlite::Result<Vec<Follow>>>()?;
+                .query_map(params.as_slice(), |r| UserCommentStat::from_row(r, &pool))?
+                .collect::<rusqlite::Result<Vec<_>>>()?;
 
             Ok(list)
         })
@@ -534,6 +562,46 @@ impl QueryRoot {
     }
 
     #[allow(clippy::too_many_arguments)]
+    #[graphql(
+        guard(TableGuard(table = "Table::Follow")),
+        complexity = "page::complexity(first, last, child_complexity)"
+    )]
+    async fn follow(
+        &self,
+        ctx: &Context<'_>,
+        #[graphql(validator(ListStringMinLength(length = "1")))] live_id: Option<Vec<String>>,
+        #[graphql(validator(IntGreaterThan(value = "-1")))] start: Option<i64>,
+        #[graphql(validator(IntGreaterThan(value = "-1")))] end: Option<i64>,
+        #[graphql(validator(IntGreaterThan(value = "0")), visible = false)] liver_uid: Option<i64>,
+        after: Option<String>,
+        before: Option<String>,
+        #[graphql(validator(IntGreaterThan(value = "-1")))] first: Option<i32>,
+        #[graphql(validator(IntGreaterThan(value = "-1")))] last: Option<i32>,
+        order_by: Option<OrderBy>,
+    ) -> Result<LiveConnection<Follow>> {
+        let pool = get_pool!(ctx, liver_uid);
+        let page = Page::new(ctx, after, before, first, last, order_by)?;
+
+        tokio::task::spawn_blocking(move || {
+            compare_start_end!(start, end);
+            let (sql, params, where_or_and) = sql_and_params!(
+                SELECT_FOLLOW;
+                (live_id, LIVE_ID);
+                (start, SEND_TIME_START),
+                (end, SEND_TIME_END)
+            );
+
+            let conn = futures::executor::block_on(pool.get())?;
+            page.query(&conn, sql, params, where_or_and)
+        })
+        .await?
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    #[graphql(
+        guard(TableGuard(table = "Table::Gift")),
+        complexity = "page::complexity(first, last, child_complexity)"
+    )]
     async fn gift(
         &self,
         ctx: &Context<'_>,
@@ -543,12 +611,18 @@ impl QueryRoot {
         #[graphql(validator(IntGreaterThan(value = "-1")))] start: Option<i64>,
         #[graphql(validator(IntGreaterThan(value = "-1")))] end: Option<i64>,
         #[graphql(validator(IntGreaterThan(value = "0")), visible = false)] liver_uid: Option<i64>,
-    ) -> Result<Vec<Gift>> {
+        after: Option<String>,
+        before: Option<String>,
+        #[graphql(validator(IntGreaterThan(value = "-1")))] first: Option<i32>,
+        #[graphql(validator(IntGreaterThan(value = "-1")))] last: Option<i32>,
+        order_by: Option<OrderBy>,
+    ) -> Result<LiveConnection<Gift>> {
         let pool = get_pool!(ctx, liver_uid);
+        let page = Page::new(ctx, after, before, first, last, order_by)?;
 
         tokio::task::spawn_blocking(move || {
             compare_start_end!(start, end);
-            let (sql, params) = sql_and_params!(
+            let (sql, params, where_or_and) = sql_and_params!(
                 SELECT_GIFT;
                 (live_id, LIVE_ID),
                 (user_id, USER_ID),
@@ -557,49 +631,138 @@ impl QueryRoot {
                 (end, SEND_TIME_END)
             );
 
+            let conn = futures::executor::block_on(pool.get())?;
+            page.query(&conn, sql, params, where_or_and)
+        })
+        .await?
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    #[graphql(
+        guard(TableGuard(table = "Table::Gift")),
+        complexity = "stat::complexity(limit, child_complexity)"
+    )]
+    async fn user_gift_stat(
+        &self,
+        ctx: &Context<'_>,
+        #[graphql(validator(ListStringMinLength(length = "1")))] live_id: Option<Vec<String>>,
+        #[graphql(validator(ListIntGreaterThan(value = "0")))] user_id: Option<Vec<i64>>,
+        #[graphql(validator(ListIntGreaterThan(value = "0")))] gift_id: Option<Vec<i64>>,
+        #[graphql(validator(IntGreaterThan(value = "-1")))] start: Option<i64>,
+        #[graphql(validator(IntGreaterThan(value = "-1")))] end: Option<i64>,
+        #[graphql(validator(IntGreaterThan(value = "0")), visible = false)] liver_uid: Option<i64>,
+        #[graphql(validator(IntGreaterThan(value = "0")))] limit: Option<i32>,
+    ) -> Result<Vec<UserGiftStat>> {
+        let pool = get_pool!(ctx, liver_uid);
+        let limit = stat::limit(limit)?;
+
+        tokio::task::spawn_blocking(move || {
+            compare_start_end!(start, end);
+            let (mut sql, mut params, mut where_or_and) = sql_and_params!(
+                SELECT_USER_GIFT_STAT;
+                (live_id, LIVE_ID),
+                (user_id, USER_ID),
+                (gift_id, GIFT_ID);
+                (start, SEND_TIME_START),
+                (end, SEND_TIME_END)
+            );
+            sql += where_or_and.next().unwrap();
+            sql += USER_ID_NOT_NULL;
+            sql += GROUP_BY_USER_ID;
+            sql += ORDER_TOTAL_VALUE_DESC;
+            sql += LIMIT;
+            sql += SEMICOLON;
+            params.push(&limit);
+
             let conn = futures::executor::block_on(pool.get())?;
             let mut stmt = conn.prepare_cached(&sql)?;
-            let gifts = stmt
-                .query_map(params.as_slice(), |r| {
-                    let medal = match r.get::<_, Option<i64>>(5)? {
-                        Some(uper_uid) => Some(MedalInfo {
-                            uper_uid,
-                            name: r.get(6)?,
-                            level: r.get(7)?,
-                        }),
-                        None => None,
-                    };
-                    let user_info = match r.get::<_, Option<i64>>(2)? {
-                        Some(user_id) => Some(UserInfo {
-                            user_id,
-                            nickname: r.get(3)?,
-                            avatar: r.get(4)?,
-                            medal,
-                            manager: r.get(8)?,
-                        }),
-                        None => None,
-                    };
-                    Ok(Gift {
-                        live_id: Arc::new(r.get(0)?),
-                        send_time: r.get(1)?,
-                        user_info,
-                        gift_id: r.get(9)?,
-                        count: r.get(10)?,
-                        combo: r.get(11)?,
-                        value: r.get(12)?,
-                        combo_id: r.get(13)?,
-                        slot_display_duration: r.get(14)?,
-                        expire_duration: r.get(15)?,
-                        draw_gift_info: r.get(16)?,
-                    })
-                })?
-                .collect::<rusqlite::Result<Vec<Gift>>>()?;
-
-            Ok(gifts)
+            let list = stmt
+                .query_map(params.as_slice(), |r| UserGiftStat::from_row(r, &pool))?
+                .collect::<rusqlite::Result<Vec<_>>>()?;
+
+            Ok(list)
+        })
+        .await?
+    }
+
+    // 用户在这个主播的直播里的数据
+    async fn user_activity(
+        &self,
+        ctx: &Context<'_>,
+        #[graphql(validator(IntGreaterThan(value = "0")))] user_id: i64,
+        #[graphql(validator(IntGreaterThan(value = "0")), visible = false)] liver_uid: Option<i64>,
+    ) -> Result<UserActivity> {
+        Ok(UserActivity::new(user_id, get_pool!(ctx, liver_uid)))
+    }
+
+    #[graphql(complexity = "stat::timeline_complexity(&kinds, child_complexity)")]
+    async fn timeline(
+        &self,
+        ctx: &Context<'_>,
+        #[graphql(validator(ListStringMinLength(length = "1")))] live_id: String,
+        #[graphql(validator(IntGreaterThan(value = "0")))] bucket_seconds: i64,
+        kinds: Option<Vec<TimelineKind>>,
+        #[graphql(validator(IntGreaterThan(value = "0")), visible = false)] liver_uid: Option<i64>,
+    ) -> Result<Timeline> {
+        let pool = get_pool!(ctx, liver_uid);
+        let bucket_millis = bucket_seconds
+            .checked_mul(1000)
+            .ok_or_else(|| anyhow!("bucket_seconds {} is too big", bucket_seconds))?;
+        let kinds = kinds.unwrap_or_else(|| TimelineKind::ALL.to_vec());
+        for kind in &kinds {
+            check_table(ctx, kind.table())?;
+        }
+
+        tokio::task::spawn_blocking(move || {
+            let conn = futures::executor::block_on(pool.get())?;
+            let start_time: i64 = match conn
+                .prepare_cached(SELECT_LIVE_START_TIME)?
+                .query_row(&[&live_id], |r| r.get(0))
+                .optional()?
+            {
+                Some(start_time) => start_time,
+                None => bail!("failed to get the start time of the live {}", live_id),
+            };
+            let mut timeline = Timeline {
+                live_id,
+                start_time,
+                bucket_seconds,
+                ..Default::default()
+            };
+
+            for kind in kinds {
+                let (sql, buckets) = match kind {
+                    TimelineKind::Comment => (SELECT_COMMENT_TIMELINE, &mut timeline.comment),
+                    TimelineKind::Gift => (SELECT_GIFT_TIMELINE, &mut timeline.gift),
+                    TimelineKind::Follow => (SELECT_FOLLOW_TIMELINE, &mut timeline.follow),
+                    TimelineKind::JoinClub => (SELECT_JOIN_CLUB_TIMELINE, &mut timeline.join_club),
+                    TimelineKind::WatchingCount => {
+                        (SELECT_WATCHING_COUNT_TIMELINE, &mut timeline.watching_count)
+                    }
+                };
+                if buckets.is_some() {
+                    continue;
+                }
+                let mut stmt = conn.prepare_cached(sql)?;
+                let list = stmt
+                    .query_map(
+                        &[&start_time as &dyn ToSql, &bucket_millis, &timeline.live_id],
+                        |r| Bucket::from_row(r, start_time, bucket_millis),
+                    )?
+                    .collect::<rusqlite::Result<Vec<_>>>()?;
+                *buckets = Some(stat::fill_buckets(list, start_time, bucket_millis));
+            }
+
+            Ok(timeline)
         })
         .await?
     }
 
+    #[allow(clippy::too_many_arguments)]
+    #[graphql(
+        guard(TableGuard(table = "Table::JoinClub")),
+        complexity = "page::complexity(first, last, child_complexity)"
+    )]
     async fn join_club(
         &self,
         ctx: &Context<'_>,
@@ -607,12 +770,18 @@ impl QueryRoot {
         #[graphql(validator(IntGreaterThan(value = "-1")))] start: Option<i64>,
         #[graphql(validator(IntGreaterThan(value = "-1")))] end: Option<i64>,
         #[graphql(validator(IntGreaterThan(value = "0")), visible = false)] liver_uid: Option<i64>,
-    ) -> Result<Vec<JoinClub>> {
+        after: Option<String>,
+        before: Option<String>,
+        #[graphql(validator(IntGreaterThan(value = "-1")))] first: Option<i32>,
+        #[graphql(validator(IntGreaterThan(value = "-1")))] last: Option<i32>,
+        order_by: Option<OrderBy>,
+    ) -> Result<LiveConnection<JoinClub>> {
         let pool = get_pool!(ctx, liver_uid);
+        let page = Page::new(ctx, after, before, first, last, order_by)?;
 
         tokio::task::spawn_blocking(move || {
             compare_start_end!(start, end);
-            let (sql, params) = sql_and_params!(
+            let (sql, params, where_or_and) = sql_and_params!(
                 SELECT_JOIN_CLUB;
                 (live_id, LIVE_ID);
                 (start, JOIN_TIME_START),
@@ -620,37 +789,16 @@ impl QueryRoot {
             );
 
             let conn = futures::executor::block_on(pool.get())?;
-            let mut stmt = conn.prepare_cached(&sql)?;
-            let list = stmt
-                .query_map(params.as_slice(), |r| {
-                    let fans_info = match r.get::<_, Option<i64>>(2)? {
-                        Some(user_id) => Some(AcFunUserInfo {
-                            user_id,
-                            nickname: r.get(3)?,
-                        }),
-                        None => None,
-                    };
-                    let uper_info = match r.get::<_, Option<i64>>(4)? {
-                        Some(user_id) => Some(AcFunUserInfo {
-                            user_id,
-                            nickname: r.get(5)?,
-                        }),
-                        None => None,
-                    };
-                    Ok(JoinClub {
-                        live_id: Arc::new(r.get(0)?),
-                        join_time: r.get(1)?,
-                        fans_info,
-                        uper_info,
-                    })
-                })?
-                .collect::<rusqlite::Result<Vec<JoinClub>>>()?;
-
-            Ok(list)
+            page.query(&conn, sql, params, where_or_and)
         })
         .await?
     }
 
+    #[allow(clippy::too_many_arguments)]
+    #[graphql(
+        guard(TableGuard(table = "Table::WatchingCount")),
+        complexity = "page::complexity(first, last, child_complexity)"
+    )]
     async fn watching_count(
         &self,
         ctx: &Context<'_>,
@@ -658,12 +806,18 @@ impl QueryRoot {
         #[graphql(validator(IntGreaterThan(value = "-1")))] start: Option<i64>,
         #[graphql(validator(IntGreaterThan(value = "-1")))] end: Option<i64>,
         #[graphql(validator(IntGreaterThan(value = "0")), visible = false)] liver_uid: Option<i64>,
-    ) -> Result<Vec<WatchingCount>> {
+        after: Option<String>,
+        before: Option<String>,
+        #[graphql(validator(IntGreaterThan(value = "-1")))] first: Option<i32>,
+        #[graphql(validator(IntGreaterThan(value = "-1")))] last: Option<i32>,
+        order_by: Option<OrderBy>,
+    ) -> Result<LiveConnection<WatchingCount>> {
         let pool = get_pool!(ctx, liver_uid);
+        let page = Page::new(ctx, after, before, first, last, order_by)?;
 
         tokio::task::spawn_blocking(move || {
             compare_start_end!(start, end);
-            let (sql, params) = sql_and_params!(
+            let (sql, params, where_or_and) = sql_and_params!(
                 SELECT_WATCHING_COUNT;
                 (live_id, LIVE_ID);
                 (start, SAVE_TIME_START),
@@ -671,18 +825,325 @@ impl QueryRoot {
             );
 
             let conn = futures::executor::block_on(pool.get())?;
-            let mut stmt = conn.prepare_cached(&sql)?;
-            let list = stmt
-                .query_map(params.as_slice(), |r| {
-                    Ok(WatchingCount {
-                        live_id: Arc::new(r.get(0)?),
-                        save_time: r.get(1)?,
-                        watching_count: r.get(2)?,
-                    })
-                })?
-                .collect::<rusqlite::Result<Vec<WatchingCount>>>()?;
+            page.query(&conn, sql, params, where_or_and)
+        })
+        .await?
+    }
 
-            Ok(list)
+    #[allow(clippy::too_many_arguments)]
+    #[graphql(
+        guard(TableGuard(table = "Table::Redpack")),
+        complexity = "page::complexity(first, last, child_complexity)"
+    )]
+    async fn redpack(
+        &self,
+        ctx: &Context<'_>,
+        #[graphql(validator(ListStringMinLength(length = "1")))] live_id: Option<Vec<String>>,
+        #[graphql(validator(ListIntGreaterThan(value = "0")))] user_id: Option<Vec<i64>>,
+        #[graphql(validator(IntGreaterThan(value = "-1")))] start: Option<i64>,
+        #[graphql(validator(IntGreaterThan(value = "-1")))] end: Option<i64>,
+        #[graphql(validator(IntGreaterThan(value = "0")), visible = false)] liver_uid: Option<i64>,
+        after: Option<String>,
+        before: Option<String>,
+        #[graphql(validator(IntGreaterThan(value = "-1")))] first: Option<i32>,
+        #[graphql(validator(IntGreaterThan(value = "-1")))] last: Option<i32>,
+        order_by: Option<OrderBy>,
+    ) -> Result<LiveConnection<Redpack>> {
+        let pool = get_pool!(ctx, liver_uid);
+        let page = Page::new(ctx, after, before, first, last, order_by)?;
+
+        tokio::task::spawn_blocking(move || {
+            compare_start_end!(start, end);
+            let (sql, params, where_or_and) = sql_and_params!(
+                SELECT_REDPACK;
+                (live_id, LIVE_ID),
+                (user_id, SENDER_USER_ID);
+                (start, SAVE_TIME_START),
+                (end, SAVE_TIME_END)
+            );
+
+            let conn = futures::executor::block_on(pool.get())?;
+            page.query(&conn, sql, params, where_or_and)
+        })
+        .await?
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    #[graphql(
+        guard(TableGuard(table = "Table::Chat")),
+        complexity = "page::complexity(first, last, child_complexity)"
+    )]
+    async fn chat_call(
+        &self,
+        ctx: &Context<'_>,
+        #[graphql(validator(ListStringMinLength(length = "1")))] live_id: Option<Vec<String>>,
+        #[graphql(validator(ListStringMinLength(length = "1")))] chat_id: Option<Vec<String>>,
+        #[graphql(validator(IntGreaterThan(value = "-1")))] start: Option<i64>,
+        #[graphql(validator(IntGreaterThan(value = "-1")))] end: Option<i64>,
+        #[graphql(validator(IntGreaterThan(value = "0")), visible = false)] liver_uid: Option<i64>,
+        after: Option<String>,
+        before: Option<String>,
+        #[graphql(validator(IntGreaterThan(value = "-1")))] first: Option<i32>,
+        #[graphql(validator(IntGreaterThan(value = "-1")))] last: Option<i32>,
+        order_by: Option<OrderBy>,
+    ) -> Result<LiveConnection<ChatCall>> {
+        let pool = get_pool!(ctx, liver_uid);
+        let page = Page::new(ctx, after, before, first, last, order_by)?;
+
+        tokio::task::spawn_blocking(move || {
+            compare_start_end!(start, end);
+            let (sql, params, where_or_and) = sql_and_params!(
+                SELECT_CHAT_CALL;
+                (live_id, LIVE_ID),
+                (chat_id, CHAT_ID);
+                (start, CALL_TIME_START),
+                (end, CALL_TIME_END)
+            );
+
+            let conn = futures::executor::block_on(pool.get())?;
+            page.query(&conn, sql, params, where_or_and)
+        })
+        .await?
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    #[graphql(
+        guard(TableGuard(table = "Table::Chat")),
+        complexity = "page::complexity(first, last, child_complexity)"
+    )]
+    async fn chat_ready(
+        &self,
+        ctx: &Context<'_>,
+        #[graphql(validator(ListStringMinLength(length = "1")))] live_id: Option<Vec<String>>,
+        #[graphql(validator(ListStringMinLength(length = "1")))] chat_id: Option<Vec<String>>,
+        #[graphql(validator(ListIntGreaterThan(value = "0")))] user_id: Option<Vec<i64>>,
+        #[graphql(validator(IntGreaterThan(value = "-1")))] start: Option<i64>,
+        #[graphql(validator(IntGreaterThan(value = "-1")))] end: Option<i64>,
+        #[graphql(validator(IntGreaterThan(value = "0")), visible = false)] liver_uid: Option<i64>,
+        after: Option<String>,
+        before: Option<String>,
+        #[graphql(validator(IntGreaterThan(value = "-1")))] first: Option<i32>,
+        #[graphql(validator(IntGreaterThan(value = "-1")))] last: Option<i32>,
+        order_by: Option<OrderBy>,
+    ) -> Result<LiveConnection<ChatReady>> {
+        let pool = get_pool!(ctx, liver_uid);
+        let page = Page::new(ctx, after, before, first, last, order_by)?;
+
+        tokio::task::spawn_blocking(move || {
+            compare_start_end!(start, end);
+            let (sql, params, where_or_and) = sql_and_params!(
+                SELECT_CHAT_READY;
+                (live_id, LIVE_ID),
+                (chat_id, CHAT_ID),
+                (user_id, GUEST_USER_ID);
+                (start, SAVE_TIME_START),
+                (end, SAVE_TIME_END)
+            );
+
+            let conn = futures::executor::block_on(pool.get())?;
+            page.query(&conn, sql, params, where_or_and)
+        })
+        .await?
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    #[graphql(
+        guard(TableGuard(table = "Table::Chat")),
+        complexity = "page::complexity(first, last, child_complexity)"
+    )]
+    async fn chat_end(
+        &self,
+        ctx: &Context<'_>,
+        #[graphql(validator(ListStringMinLength(length = "1")))] live_id: Option<Vec<String>>,
+        #[graphql(validator(ListStringMinLength(length = "1")))] chat_id: Option<Vec<String>>,
+        #[graphql(validator(IntGreaterThan(value = "-1")))] start: Option<i64>,
+        #[graphql(validator(IntGreaterThan(value = "-1")))] end: Option<i64>,
+        #[graphql(validator(IntGreaterThan(value = "0")), visible = false)] liver_uid: Option<i64>,
+        after: Option<String>,
+        before: Option<String>,
+        #[graphql(validator(IntGreaterThan(value = "-1")))] first: Option<i32>,
+        #[graphql(validator(IntGreaterThan(value = "-1")))] last: Option<i32>,
+        order_by: Option<OrderBy>,
+    ) -> Result<LiveConnection<ChatEnd>> {
+        let pool = get_pool!(ctx, liver_uid);
+        let page = Page::new(ctx, after, before, first, last, order_by)?;
+
+        tokio::task::spawn_blocking(move || {
+            compare_start_end!(start, end);
+            let (sql, params, where_or_and) = sql_and_params!(
+                SELECT_CHAT_END;
+                (live_id, LIVE_ID),
+                (chat_id, CHAT_ID);
+                (start, SAVE_TIME_START),
+                (end, SAVE_TIME_END)
+            );
+
+            let conn = futures::executor::block_on(pool.get())?;
+            page.query(&conn, sql, params, where_or_and)
+        })
+        .await?
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    #[graphql(
+        guard(TableGuard(table = "Table::AuthorChat")),
+        complexity = "page::complexity(first, last, child_complexity)"
+    )]
+    async fn author_chat_call(
+        &self,
+        ctx: &Context<'_>,
+        #[graphql(validator(ListStringMinLength(length = "1")))] live_id: Option<Vec<String>>,
+        #[graphql(validator(ListStringMinLength(length = "1")))] author_chat_id: Option<
+            Vec<String>,
+        >,
+        #[graphql(validator(ListIntGreaterThan(value = "0")))] user_id: Option<Vec<i64>>,
+        #[graphql(validator(IntGreaterThan(value = "-1")))] start: Option<i64>,
+        #[graphql(validator(IntGreaterThan(value = "-1")))] end: Option<i64>,
+        #[graphql(validator(IntGreaterThan(value = "0")), visible = false)] liver_uid: Option<i64>,
+        after: Option<String>,
+        before: Option<String>,
+        #[graphql(validator(IntGreaterThan(value = "-1")))] first: Option<i32>,
+        #[graphql(validator(IntGreaterThan(value = "-1")))] last: Option<i32>,
+        order_by: Option<OrderBy>,
+    ) -> Result<LiveConnection<AuthorChatCall>> {
+        let pool = get_pool!(ctx, liver_uid);
+        let page = Page::new(ctx, after, before, first, last, order_by)?;
+
+        tokio::task::spawn_blocking(move || {
+            compare_start_end!(start, end);
+            let (sql, params, where_or_and) = sql_and_params!(
+                SELECT_AUTHOR_CHAT_CALL;
+                (live_id, LIVE_ID),
+                (author_chat_id, AUTHOR_CHAT_ID),
+                (user_id, INVITER_USER_ID);
+                (start, CALL_TIME_START),
+                (end, CALL_TIME_END)
+            );
+
+            let conn = futures::executor::block_on(pool.get())?;
+            page.query(&conn, sql, params, where_or_and)
+        })
+        .await?
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    #[graphql(
+        guard(TableGuard(table = "Table::AuthorChat")),
+        complexity = "page::complexity(first, last, child_complexity)"
+    )]
+    async fn author_chat_ready(
+        &self,
+        ctx: &Context<'_>,
+        #[graphql(validator(ListStringMinLength(length = "1")))] live_id: Option<Vec<String>>,
+        #[graphql(validator(ListStringMinLength(length = "1")))] author_chat_id: Option<
+            Vec<String>,
+        >,
+        #[graphql(validator(ListIntGreaterThan(value = "0")))] user_id: Option<Vec<i64>>,
+        #[graphql(validator(IntGreaterThan(value = "-1")))] start: Option<i64>,
+        #[graphql(validator(IntGreaterThan(value = "-1")))] end: Option<i64>,
+        #[graphql(validator(IntGreaterThan(value = "0")), visible = false)] liver_uid: Option<i64>,
+        after: Option<String>,
+        before: Option<String>,
+        #[graphql(validator(IntGreaterThan(value = "-1")))] first: Option<i32>,
+        #[graphql(validator(IntGreaterThan(value = "-1")))] last: Option<i32>,
+        order_by: Option<OrderBy>,
+    ) -> Result<LiveConnection<AuthorChatReady>> {
+        let pool = get_pool!(ctx, liver_uid);
+        let page = Page::new(ctx, after, before, first, last, order_by)?;
+
+        tokio::task::spawn_blocking(move || {
+            compare_start_end!(start, end);
+            let (sql, params, where_or_and) = sql_and_params!(
+                SELECT_AUTHOR_CHAT_READY;
+                (live_id, LIVE_ID),
+                (author_chat_id, AUTHOR_CHAT_ID),
+                (user_id, INVITER_OR_INVITEE_USER_ID);
+                (start, SAVE_TIME_START),
+                (end, SAVE_TIME_END)
+            );
+
+            let conn = futures::executor::block_on(pool.get())?;
+            page.query(&conn, sql, params, where_or_and)
+        })
+        .await?
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    #[graphql(
+        guard(TableGuard(table = "Table::AuthorChat")),
+        complexity = "page::complexity(first, last, child_complexity)"
+    )]
+    async fn author_chat_end(
+        &self,
+        ctx: &Context<'_>,
+        #[graphql(validator(ListStringMinLength(length = "1")))] live_id: Option<Vec<String>>,
+        #[graphql(validator(ListStringMinLength(length = "1")))] author_chat_id: Option<
+            Vec<String>,
+        >,
+        #[graphql(validator(IntGreaterThan(value = "-1")))] start: Option<i64>,
+        #[graphql(validator(IntGreaterThan(value = "-1")))] end: Option<i64>,
+        #[graphql(validator(IntGreaterThan(value = "0")), visible = false)] liver_uid: Option<i64>,
+        after: Option<String>,
+        before: Option<String>,
+        #[graphql(validator(IntGreaterThan(value = "-1")))] first: Option<i32>,
+        #[graphql(validator(IntGreaterThan(value = "-1")))] last: Option<i32>,
+        order_by: Option<OrderBy>,
+    ) -> Result<LiveConnection<AuthorChatEnd>> {
+        let pool = get_pool!(ctx, liver_uid);
+        let page = Page::new(ctx, after, before, first, last, order_by)?;
+
+        tokio::task::spawn_blocking(move || {
+            compare_start_end!(start, end);
+            let (sql, params, where_or_and) = sql_and_params!(
+                SELECT_AUTHOR_CHAT_END;
+                (live_id, LIVE_ID),
+                (author_chat_id, AUTHOR_CHAT_ID);
+                (start, SAVE_TIME_START),
+                (end, SAVE_TIME_END)
+            );
+
+            let conn = futures::executor::block_on(pool.get())?;
+            page.query(&conn, sql, params, where_or_and)
+        })
+        .await?
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    #[graphql(
+        guard(TableGuard(table = "Table::AuthorChat")),
+        complexity = "page::complexity(first, last, child_complexity)"
+    )]
+    async fn author_chat_change_sound_config(
+        &self,
+        ctx: &Context<'_>,
+        #[graphql(validator(ListStringMinLength(length = "1")))] live_id: Option<Vec<String>>,
+        #[graphql(validator(ListStringMinLength(length = "1")))] author_chat_id: Option<
+            Vec<String>,
+        >,
+        #[graphql(validator(IntGreaterThan(value = "-1")))] start: Option<i64>,
+        #[graphql(validator(IntGreaterThan(value = "-1")))] end: Option<i64>,
+        #[graphql(validator(IntGreaterThan(value = "0")), visible = false)] liver_uid: Option<i64>,
+        after: Option<String>,
+        before: Option<String>,
+        #[graphql(validator(IntGreaterThan(value = "-1")))] first: Option<i32>,
+        #[graphql(validator(IntGreaterThan(value = "-1")))] last: Option<i32>,
+        order_by: Option<OrderBy>,
+    ) -> Result<LiveConnection<AuthorChatChangeSoundConfig>> {
+        let pool = get_pool!(ctx, liver_uid);
+        let page = Page::new(ctx, after, before, first, last, order_by)?;
+
+        tokio::task::spawn_blocking(move || {
+            compare_start_end!(start, end);
+            let (sql, params, where_or_and) = sql_and_params!(
+                SELECT_AUTHOR_CHAT_CHANGE_SOUND_CONFIG;
+                (live_id, LIVE_ID),
+                (author_chat_id, AUTHOR_CHAT_ID);
+                (start, SAVE_TIME_START),
+                (end, SAVE_TIME_END)
+            );
+
+            let conn = futures::executor::block_on(pool.get())?;
+            page.query(&conn, sql, params, where_or_and)
         })
         .await?
     }
@@ -696,11 +1157,10 @@ async fn cache_gift_info(
     let pool = connect(GIFT_DATABASE.clone()).await?;
 
     tokio::task::spawn_blocking(move || {
-        let (mut sql, params) = sql_and_params!(
+        let (mut sql, params, _) = sql_and_params!(
             SELECT_GIFT_INFO;
             (gift_id, GIFT_ID);
         );
-        let _ = sql.pop();
         sql += ORDER_SAVE_TIME_DESC;
         sql += SEMICOLON;
 
@@ -770,72 +1230,3 @@ fn gift_info(
 
     Ok(list)
 }
-
-#[inline]
-fn title(conn: &Connection, sql: &str, params: &[&dyn ToSql]) -> rusqlite::Result<Vec<Title>> {
-    let mut stmt = conn.prepare_cached(sql)?;
-    let titles = stmt
-        .query_map(params, |r| {
-            Ok(Title {
-                live_id: Arc::new(r.get(0)?),
-                save_time: r.get(1)?,
-                title: r.get(2)?,
-            })
-        })?
-        .collect::<rusqlite::Result<Vec<Title>>>()?;
-
-    Ok(titles)
-}
-
-#[inline]
-fn liver_info(
-    conn: &Connection,
-    sql: &str,
-    params: &[&dyn ToSql],
-) -> rusqlite::Result<Vec<LiverInfo>> {
-    let mut stmt = conn.prepare_cached(sql)?;
-    let list = stmt
-        .query_map(params, |r| {
-            Ok(LiverInfo {
-                live_id: Arc::new(r.get(0)?),
-                save_time: r.get(1)?,
-                liver_uid: r.get(2)?,
-                nickname: r.get(3)?,
-                avatar: r.get(4)?,
-                avatar_frame: r.get(5)?,
-                following_count: r.get(6)?,
-                contribute_count: r.get(7)?,
-                live_begin_fans_count: r.get(8)?,
-                live_end_fans_count: r.get(9)?,
-                signature: r.get(10)?,
-                verified_text: r.get(11)?,
-                is_join_up_college: r.get(12)?,
-                medal_name: r.get(13)?,
-                live_begin_medal_count: r.get(14)?,
-                live_end_medal_count: r.get(15)?,
-            })
-        })?
-        .collect::<rusqlite::Result<Vec<LiverInfo>>>()?;
-
-    Ok(list)
-}
-
-#[inline]
-fn summary(conn: &Connection, sql: &str, params: &[&dyn ToSql]) -> rusqlite::Result<Vec<Summary>> {
-    let mut stmt = conn.prepare_cached(sql)?;
-    let summaries = stmt
-        .query_map(params, |r| {
-            Ok(Summary {
-                live_id: Arc::new(r.get(0)?),
-                save_time: r.get(1)?,
-                duration: r.get(2)?,
-                like_count: r.get(3)?,
-                watch_total_count: r.get(4)?,
-                watch_online_max_count: r.get(5)?,
-                banana_count: r.get(6)?,
-            })
-        })?
-        .collect::<rusqlite::Result<Vec<Summary>>>()?;
-
-    Ok(summaries)
-}
@@ -0,0 +1,190 @@
+use crate::{
+    config::{TokenMeta, User, CONFIG, DEFAULT_TOKEN_NAME},
+    extension::{QueryLimit, QueryLimitInput},
+    limit::{RateLimit, RateLimitInput},
+    scope::ScopeInput,
+};
+use anyhow::{bail, Result};
+use async_graphql::{
+    validators::{IntGreaterThan, StringMinLength},
+    Context, Object, SimpleObject,
+};
+
+#[derive(Clone, Copy, Debug)]
+pub struct MutationRoot;
+
+macro_rules! check_admin {
+    ($ctx:expr) => {
+        if !($ctx).data_unchecked::<User>().is_admin() {
+            bail!("the admin authorization is needed");
+        }
+    };
+}
+
+// `exist`为添加前是否已经存在该主播，存在的话不会生成新的token
+#[derive(Clone, Debug, SimpleObject)]
+pub struct AddLiverPayload {
+    pub liver_uid: i64,
+    pub exist: bool,
+    pub token: Option<String>,
+}
+
+#[derive(Clone, Copy, Debug, SimpleObject)]
+pub struct DeleteLiverPayload {
+    pub liver_uid: i64,
+    pub exist: bool,
+}
+
+#[derive(Clone, Debug, SimpleObject)]
+pub struct AddTokenPayload {
+    pub token: String,
+    pub meta: TokenMeta,
+}
+
+#[derive(Clone, Debug, SimpleObject)]
+pub struct RevokeTokenPayload {
+    pub liver_uid: i64,
+    pub name: String,
+    pub exist: bool,
+}
+
+#[derive(Clone, Debug, SimpleObject)]
+pub struct RegenerateTokenPayload {
+    pub liver_uid: i64,
+    pub name: String,
+    pub token: String,
+}
+
+#[Object]
+impl MutationRoot {
+    async fn add_liver(
+        &self,
+        ctx: &Context<'_>,
+        #[graphql(validator(IntGreaterThan(value = "0")))] liver_uid: i64,
+    ) -> Result<AddLiverPayload> {
+        check_admin!(ctx);
+        let mut config = CONFIG.get().expect("failed to get CONFIG").write().await;
+        let info = config.add_liver(liver_uid, false).await?;
+        config.save_config().await?;
+
+        Ok(AddLiverPayload {
+            liver_uid,
+            exist: info.exist,
+            token: info.token,
+        })
+    }
+
+    async fn delete_liver(
+        &self,
+        ctx: &Context<'_>,
+        #[graphql(validator(IntGreaterThan(value = "0")))] liver_uid: i64,
+    ) -> Result<DeleteLiverPayload> {
+        check_admin!(ctx);
+        let mut config = CONFIG.get().expect("failed to get CONFIG").write().await;
+        let info = config.delete_liver(liver_uid, false).await?;
+        config.save_config().await?;
+
+        Ok(DeleteLiverPayload {
+            liver_uid,
+            exist: info.exist,
+        })
+    }
+
+    async fn add_token(
+        &self,
+        ctx: &Context<'_>,
+        #[graphql(validator(IntGreaterThan(value = "0")))] liver_uid: i64,
+        #[graphql(validator(StringMinLength(length = "1")))] name: String,
+        #[graphql(validator(IntGreaterThan(value = "0")))] expire_time: Option<i64>,
+        scope: Option<ScopeInput>,
+    ) -> Result<AddTokenPayload> {
+        check_admin!(ctx);
+        let mut config = CONFIG.get().expect("failed to get CONFIG").write().await;
+        let (token, meta) =
+            config.add_token(liver_uid, name, expire_time, scope.map(Into::into))?;
+        config.save_config().await?;
+
+        Ok(AddTokenPayload { token, meta })
+    }
+
+    async fn revoke_token(
+        &self,
+        ctx: &Context<'_>,
+        #[graphql(validator(IntGreaterThan(value = "0")))] liver_uid: i64,
+        #[graphql(validator(StringMinLength(length = "1")))] name: String,
+    ) -> Result<RevokeTokenPayload> {
+        check_admin!(ctx);
+        let mut config = CONFIG.get().expect("failed to get CONFIG").write().await;
+        let exist = config.revoke_token(liver_uid, &name);
+        config.save_config().await?;
+
+        Ok(RevokeTokenPayload {
+            liver_uid,
+            name,
+            exist,
+        })
+    }
+
+    async fn regenerate_token(
+        &self,
+        ctx: &Context<'_>,
+        #[graphql(validator(IntGreaterThan(value = "0")))] liver_uid: i64,
+        #[graphql(validator(StringMinLength(length = "1")))] name: Option<String>,
+    ) -> Result<RegenerateTokenPayload> {
+        check_admin!(ctx);
+        let name = name.unwrap_or_else(|| DEFAULT_TOKEN_NAME.to_string());
+        let mut config = CONFIG.get().expect("failed to get CONFIG").write().await;
+        let token = config.regenerate_token(liver_uid, &name)?;
+        config.save_config().await?;
+
+        Ok(RegenerateTokenPayload {
+            liver_uid,
+            name,
+            token,
+        })
+    }
+
+    // `liver_uid`为空时设置默认的限制
+    async fn set_rate_limit(
+        &self,
+        ctx: &Context<'_>,
+        #[graphql(validator(IntGreaterThan(value = "0")))] liver_uid: Option<i64>,
+        limit: RateLimitInput,
+    ) -> Result<RateLimit> {
+        check_admin!(ctx);
+        let limit = limit.into();
+        let mut config = CONFIG.get().expect("failed to get CONFIG").write().await;
+        config.set_rate_limit(liver_uid, limit)?;
+        config.save_config().await?;
+
+        Ok(limit)
+    }
+
+    // 删除主播单独设置的限制，之后使用默认的限制
+    async fn delete_rate_limit(
+        &self,
+        ctx: &Context<'_>,
+        #[graphql(validator(IntGreaterThan(value = "0")))] liver_uid: i64,
+    ) -> Result<bool> {
+        check_admin!(ctx);
+        let mut config = CONFIG.get().expect("failed to get CONFIG").write().await;
+        let exist = config.delete_rate_limit(liver_uid);
+        config.save_config().await?;
+
+        Ok(exist)
+    }
+
+    async fn set_query_limit(
+        &self,
+        ctx: &Context<'_>,
+        limit: QueryLimitInput,
+    ) -> Result<QueryLimit> {
+        check_admin!(ctx);
+        let limit = limit.into();
+        let mut config = CONFIG.get().expect("failed to get CONFIG").write().await;
+        config.set_query_limit(limit);
+        config.save_config().await?;
+
+        Ok(limit)
+    }
+}
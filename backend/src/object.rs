@@ -0,0 +1,774 @@
+use crate::{
+    loader::{LiveLoader, LiverInfoKey, SummaryKey, TitleKey},
+    page::{self, LiveConnection, OrderBy, Page},
+    pool::RusqliteConnectionManager,
+    row::FromRow,
+    scope::{Table, TableGuard},
+    sql::*,
+    sqlite::connect,
+    stat::{self, UserCommentStat, UserGiftStat},
+};
+use acfunlivedata_common::{
+    data::{
+        Comment, Follow, Gift, JoinClub, Live, LiveId, LiveInfo, LiveType, LiverInfo, Redpack,
+        Summary, Title, WatchingCount,
+    },
+    database::liver_db_path,
+};
+use anyhow::{anyhow, Result};
+use async_graphql::{dataloader::DataLoader, validators::IntGreaterThan, Context, Object};
+use bb8::Pool;
+use rusqlite::{types::Value, Row, ToSql};
+use std::{iter, sync::Arc};
+
+// `filters`为查询条件和对应的参数，条件之间用AND连接
+async fn query_page<T>(
+    pool: Pool<RusqliteConnectionManager>,
+    page: Page,
+    select: &'static str,
+    filters: Vec<(&'static str, Value)>,
+) -> Result<LiveConnection<T>>
+where
+    T: FromRow + Send + 'static,
+{
+    tokio::task::spawn_blocking(move || {
+        let mut sql = select.to_string();
+        let mut where_or_and = iter::once(WHERE).chain(iter::repeat(AND));
+        for (filter, _) in &filters {
+            sql += where_or_and.next().unwrap();
+            sql += filter;
+        }
+        let params = filters.iter().map(|(_, v)| v as &dyn ToSql).collect();
+
+        let conn = futures::executor::block_on(pool.get())?;
+        page.query(&conn, sql, params, where_or_and)
+    })
+    .await?
+}
+
+// 按`order`排序的前`limit`个用户的统计
+async fn query_stat<T, F>(
+    pool: Pool<RusqliteConnectionManager>,
+    select: &'static str,
+    order: &'static str,
+    live_id: &LiveId,
+    limit: i64,
+    f: F,
+) -> Result<Vec<T>>
+where
+    T: Send + 'static,
+    F: Fn(&Row<'_>, &Pool<RusqliteConnectionManager>) -> rusqlite::Result<T> + Send + 'static,
+{
+    let live_id = live_id.to_string();
+    tokio::task::spawn_blocking(move || {
+        let mut sql = select.to_string();
+        sql += WHERE;
+        sql += LIVE_ID;
+        sql += AND;
+        sql += USER_ID_NOT_NULL;
+        sql += GROUP_BY_USER_ID;
+        sql += order;
+        sql += LIMIT;
+        sql += SEMICOLON;
+
+        let conn = futures::executor::block_on(pool.get())?;
+        let mut stmt = conn.prepare_cached(&sql)?;
+        let list = stmt
+            .query_map(&[&live_id as &dyn ToSql, &limit], |r| f(r, &pool))?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+
+        Ok(list)
+    })
+    .await?
+}
+
+#[inline]
+fn live_id_filter(live_id: &LiveId) -> Vec<(&'static str, Value)> {
+    vec![(LIVE_ID, Value::Text(live_id.to_string()))]
+}
+
+// `title`、`liver_info`和`summary`通过DataLoader批量查询，其它嵌套字段按分页查询这场直播的数据
+#[derive(Clone)]
+pub struct LiveInfoNode {
+    info: LiveInfo,
+    loader: Arc<DataLoader<LiveLoader>>,
+    pool: Pool<RusqliteConnectionManager>,
+}
+
+impl LiveInfoNode {
+    #[inline]
+    pub fn new(
+        info: LiveInfo,
+        loader: Arc<DataLoader<LiveLoader>>,
+        pool: Pool<RusqliteConnectionManager>,
+    ) -> Self {
+        Self { info, loader, pool }
+    }
+}
+
+#[Object(name = "LiveInfo")]
+impl LiveInfoNode {
+    #[inline]
+    async fn live_id(&self) -> &str {
+        &self.info.live_id
+    }
+
+    #[inline]
+    async fn liver_uid(&self) -> i64 {
+        self.info.liver_uid
+    }
+
+    #[graphql(guard(TableGuard(table = "Table::LiverInfo")))]
+    async fn liver_info(&self) -> Result<Option<LiverInfo>> {
+        self.loader
+            .load_one(LiverInfoKey(self.info.live_id.clone()))
+            .await
+            .map_err(|e| anyhow!("failed to load liver info: {}", e))
+    }
+
+    #[inline]
+    async fn stream_name(&self) -> &str {
+        &self.info.stream_name
+    }
+
+    #[inline]
+    async fn start_time(&self) -> i64 {
+        self.info.start_time
+    }
+
+    #[graphql(guard(TableGuard(table = "Table::Title")))]
+    async fn title(&self) -> Result<Option<Vec<Title>>> {
+        Ok(Some(
+            self.loader
+                .load_one(TitleKey(self.info.live_id.clone()))
+                .await
+                .map_err(|e| anyhow!("failed to load title: {}", e))?
+                .unwrap_or_default(),
+        ))
+    }
+
+    #[inline]
+    async fn cover(&self) -> Option<&str> {
+        self.info.cover.as_deref()
+    }
+
+    #[inline]
+    async fn live_type(&self) -> Option<&LiveType> {
+        self.info.live_type.as_ref()
+    }
+
+    #[inline]
+    async fn has_fans_club(&self) -> bool {
+        self.info.has_fans_club
+    }
+
+    #[inline]
+    async fn portrait(&self) -> bool {
+        self.info.portrait
+    }
+
+    #[inline]
+    async fn panoramic(&self) -> bool {
+        self.info.panoramic
+    }
+
+    #[inline]
+    async fn disable_danmaku_show(&self) -> bool {
+        self.info.disable_danmaku_show
+    }
+
+    #[inline]
+    async fn paid_show_user_buy_status(&self) -> bool {
+        self.info.paid_show_user_buy_status
+    }
+
+    #[graphql(guard(TableGuard(table = "Table::Summary")))]
+    async fn summary(&self) -> Result<Option<Summary>> {
+        self.loader
+            .load_one(SummaryKey(self.info.live_id.clone()))
+            .await
+            .map_err(|e| anyhow!("failed to load summary: {}", e))
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    #[graphql(
+        guard(TableGuard(table = "Table::Comment")),
+        complexity = "page::complexity(first, last, child_complexity)"
+    )]
+    async fn comments(
+        &self,
+        ctx: &Context<'_>,
+        after: Option<String>,
+        before: Option<String>,
+        #[graphql(validator(IntGreaterThan(value = "-1")))] first: Option<i32>,
+        #[graphql(validator(IntGreaterThan(value = "-1")))] last: Option<i32>,
+        order_by: Option<OrderBy>,
+    ) -> Result<LiveConnection<Comment>> {
+        let page = Page::new(ctx, after, before, first, last, order_by)?;
+        query_page(
+            self.pool.clone(),
+            page,
+            SELECT_COMMENT,
+            live_id_filter(&self.info.live_id),
+        )
+        .await
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    #[graphql(
+        guard(TableGuard(table = "Table::Gift")),
+        complexity = "page::complexity(first, last, child_complexity)"
+    )]
+    async fn gifts(
+        &self,
+        ctx: &Context<'_>,
+        after: Option<String>,
+        before: Option<String>,
+        #[graphql(validator(IntGreaterThan(value = "-1")))] first: Option<i32>,
+        #[graphql(validator(IntGreaterThan(value = "-1")))] last: Option<i32>,
+        order_by: Option<OrderBy>,
+    ) -> Result<LiveConnection<Gift>> {
+        let page = Page::new(ctx, after, before, first, last, order_by)?;
+        query_page(
+            self.pool.clone(),
+            page,
+            SELECT_GIFT,
+            live_id_filter(&self.info.live_id),
+        )
+        .await
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    #[graphql(
+        guard(TableGuard(table = "Table::Follow")),
+        complexity = "page::complexity(first, last, child_complexity)"
+    )]
+    async fn follows(
+        &self,
+        ctx: &Context<'_>,
+        after: Option<String>,
+        before: Option<String>,
+        #[graphql(validator(IntGreaterThan(value = "-1")))] first: Option<i32>,
+        #[graphql(validator(IntGreaterThan(value = "-1")))] last: Option<i32>,
+        order_by: Option<OrderBy>,
+    ) -> Result<LiveConnection<Follow>> {
+        let page = Page::new(ctx, after, before, first, last, order_by)?;
+        query_page(
+            self.pool.clone(),
+            page,
+            SELECT_FOLLOW,
+            live_id_filter(&self.info.live_id),
+        )
+        .await
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    #[graphql(
+        guard(TableGuard(table = "Table::JoinClub")),
+        complexity = "page::complexity(first, last, child_complexity)"
+    )]
+    async fn join_clubs(
+        &self,
+        ctx: &Context<'_>,
+        after: Option<String>,
+        before: Option<String>,
+        #[graphql(validator(IntGreaterThan(value = "-1")))] first: Option<i32>,
+        #[graphql(validator(IntGreaterThan(value = "-1")))] last: Option<i32>,
+        order_by: Option<OrderBy>,
+    ) -> Result<LiveConnection<JoinClub>> {
+        let page = Page::new(ctx, after, before, first, last, order_by)?;
+        query_page(
+            self.pool.clone(),
+            page,
+            SELECT_JOIN_CLUB,
+            live_id_filter(&self.info.live_id),
+        )
+        .await
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    #[graphql(
+        guard(TableGuard(table = "Table::WatchingCount")),
+        complexity = "page::complexity(first, last, child_complexity)"
+    )]
+    async fn watching_counts(
+        &self,
+        ctx: &Context<'_>,
+        after: Option<String>,
+        before: Option<String>,
+        #[graphql(validator(IntGreaterThan(value = "-1")))] first: Option<i32>,
+        #[graphql(validator(IntGreaterThan(value = "-1")))] last: Option<i32>,
+        order_by: Option<OrderBy>,
+    ) -> Result<LiveConnection<WatchingCount>> {
+        let page = Page::new(ctx, after, before, first, last, order_by)?;
+        query_page(
+            self.pool.clone(),
+            page,
+            SELECT_WATCHING_COUNT,
+            live_id_filter(&self.info.live_id),
+        )
+        .await
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    #[graphql(
+        guard(TableGuard(table = "Table::Redpack")),
+        complexity = "page::complexity(first, last, child_complexity)"
+    )]
+    async fn redpacks(
+        &self,
+        ctx: &Context<'_>,
+        after: Option<String>,
+        before: Option<String>,
+        #[graphql(validator(IntGreaterThan(value = "-1")))] first: Option<i32>,
+        #[graphql(validator(IntGreaterThan(value = "-1")))] last: Option<i32>,
+        order_by: Option<OrderBy>,
+    ) -> Result<LiveConnection<Redpack>> {
+        let page = Page::new(ctx, after, before, first, last, order_by)?;
+        query_page(
+            self.pool.clone(),
+            page,
+            SELECT_REDPACK,
+            live_id_filter(&self.info.live_id),
+        )
+        .await
+    }
+
+    #[graphql(
+        guard(TableGuard(table = "Table::Gift")),
+        complexity = "stat::complexity(limit, child_complexity)"
+    )]
+    async fn user_gift_stat(
+        &self,
+        #[graphql(validator(IntGreaterThan(value = "0")))] limit: Option<i32>,
+    ) -> Result<Vec<UserGiftStat>> {
+        query_stat(
+            self.pool.clone(),
+            SELECT_USER_GIFT_STAT,
+            ORDER_TOTAL_VALUE_DESC,
+            &self.info.live_id,
+            stat::limit(limit)?,
+            UserGiftStat::from_row,
+        )
+        .await
+    }
+
+    #[graphql(
+        guard(TableGuard(table = "Table::Comment")),
+        complexity = "stat::complexity(limit, child_complexity)"
+    )]
+    async fn user_comment_stat(
+        &self,
+        #[graphql(validator(IntGreaterThan(value = "0")))] limit: Option<i32>,
+    ) -> Result<Vec<UserCommentStat>> {
+        query_stat(
+            self.pool.clone(),
+            SELECT_USER_COMMENT_STAT,
+            ORDER_COMMENT_COUNT_DESC,
+            &self.info.live_id,
+            stat::limit(limit)?,
+            UserCommentStat::from_row,
+        )
+        .await
+    }
+}
+
+// 管理员查询的直播，嵌套字段查询对应主播的数据库
+#[derive(Clone, Debug)]
+pub struct LiveNode {
+    live: Live,
+}
+
+impl LiveNode {
+    #[inline]
+    pub fn new(live: Live) -> Self {
+        Self { live }
+    }
+
+    #[inline]
+    async fn pool(&self) -> Result<Pool<RusqliteConnectionManager>> {
+        connect(liver_db_path(self.live.liver_uid)).await
+    }
+}
+
+#[Object(name = "Live")]
+impl LiveNode {
+    #[inline]
+    async fn live_id(&self) -> &str {
+        &self.live.live_id
+    }
+
+    #[inline]
+    async fn liver_uid(&self) -> i64 {
+        self.live.liver_uid
+    }
+
+    #[inline]
+    async fn nickname(&self) -> &str {
+        &self.live.nickname
+    }
+
+    #[inline]
+    async fn stream_name(&self) -> &str {
+        &self.live.stream_name
+    }
+
+    #[inline]
+    async fn start_time(&self) -> i64 {
+        self.live.start_time
+    }
+
+    #[inline]
+    async fn title(&self) -> Option<&str> {
+        self.live.title.as_deref()
+    }
+
+    #[inline]
+    async fn live_type(&self) -> Option<&LiveType> {
+        self.live.live_type.as_ref()
+    }
+
+    #[inline]
+    async fn portrait(&self) -> Option<bool> {
+        self.live.portrait
+    }
+
+    #[inline]
+    async fn panoramic(&self) -> Option<bool> {
+        self.live.panoramic
+    }
+
+    #[inline]
+    async fn disable_danmaku_show(&self) -> Option<bool> {
+        self.live.disable_danmaku_show
+    }
+
+    #[inline]
+    async fn duration(&self) -> Option<i64> {
+        self.live.duration
+    }
+
+    #[inline]
+    async fn like_count(&self) -> Option<&str> {
+        self.live.like_count.as_deref()
+    }
+
+    #[inline]
+    async fn watch_count(&self) -> Option<&str> {
+        self.live.watch_count.as_deref()
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    #[graphql(complexity = "page::complexity(first, last, child_complexity)")]
+    async fn comments(
+        &self,
+        ctx: &Context<'_>,
+        after: Option<String>,
+        before: Option<String>,
+        #[graphql(validator(IntGreaterThan(value = "-1")))] first: Option<i32>,
+        #[graphql(validator(IntGreaterThan(value = "-1")))] last: Option<i32>,
+        order_by: Option<OrderBy>,
+    ) -> Result<LiveConnection<Comment>> {
+        let page = Page::new(ctx, after, before, first, last, order_by)?;
+        query_page(
+            self.pool().await?,
+            page,
+            SELECT_COMMENT,
+            live_id_filter(&self.live.live_id),
+        )
+        .await
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    #[graphql(complexity = "page::complexity(first, last, child_complexity)")]
+    async fn gifts(
+        &self,
+        ctx: &Context<'_>,
+        after: Option<String>,
+        before: Option<String>,
+        #[graphql(validator(IntGreaterThan(value = "-1")))] first: Option<i32>,
+        #[graphql(validator(IntGreaterThan(value = "-1")))] last: Option<i32>,
+        order_by: Option<OrderBy>,
+    ) -> Result<LiveConnection<Gift>> {
+        let page = Page::new(ctx, after, before, first, last, order_by)?;
+        query_page(
+            self.pool().await?,
+            page,
+            SELECT_GIFT,
+            live_id_filter(&self.live.live_id),
+        )
+        .await
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    #[graphql(complexity = "page::complexity(first, last, child_complexity)")]
+    async fn follows(
+        &self,
+        ctx: &Context<'_>,
+        after: Option<String>,
+        before: Option<String>,
+        #[graphql(validator(IntGreaterThan(value = "-1")))] first: Option<i32>,
+        #[graphql(validator(IntGreaterThan(value = "-1")))] last: Option<i32>,
+        order_by: Option<OrderBy>,
+    ) -> Result<LiveConnection<Follow>> {
+        let page = Page::new(ctx, after, before, first, last, order_by)?;
+        query_page(
+            self.pool().await?,
+            page,
+            SELECT_FOLLOW,
+            live_id_filter(&self.live.live_id),
+        )
+        .await
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    #[graphql(complexity = "page::complexity(first, last, child_complexity)")]
+    async fn join_clubs(
+        &self,
+        ctx: &Context<'_>,
+        after: Option<String>,
+        before: Option<String>,
+        #[graphql(validator(IntGreaterThan(value = "-1")))] first: Option<i32>,
+        #[graphql(validator(IntGreaterThan(value = "-1")))] last: Option<i32>,
+        order_by: Option<OrderBy>,
+    ) -> Result<LiveConnection<JoinClub>> {
+        let page = Page::new(ctx, after, before, first, last, order_by)?;
+        query_page(
+            self.pool().await?,
+            page,
+            SELECT_JOIN_CLUB,
+            live_id_filter(&self.live.live_id),
+        )
+        .await
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    #[graphql(complexity = "page::complexity(first, last, child_complexity)")]
+    async fn watching_counts(
+        &self,
+        ctx: &Context<'_>,
+        after: Option<String>,
+        before: Option<String>,
+        #[graphql(validator(IntGreaterThan(value = "-1")))] first: Option<i32>,
+        #[graphql(validator(IntGreaterThan(value = "-1")))] last: Option<i32>,
+        order_by: Option<OrderBy>,
+    ) -> Result<LiveConnection<WatchingCount>> {
+        let page = Page::new(ctx, after, before, first, last, order_by)?;
+        query_page(
+            self.pool().await?,
+            page,
+            SELECT_WATCHING_COUNT,
+            live_id_filter(&self.live.live_id),
+        )
+        .await
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    #[graphql(complexity = "page::complexity(first, last, child_complexity)")]
+    async fn redpacks(
+        &self,
+        ctx: &Context<'_>,
+        after: Option<String>,
+        before: Option<String>,
+        #[graphql(validator(IntGreaterThan(value = "-1")))] first: Option<i32>,
+        #[graphql(validator(IntGreaterThan(value = "-1")))] last: Option<i32>,
+        order_by: Option<OrderBy>,
+    ) -> Result<LiveConnection<Redpack>> {
+        let page = Page::new(ctx, after, before, first, last, order_by)?;
+        query_page(
+            self.pool().await?,
+            page,
+            SELECT_REDPACK,
+            live_id_filter(&self.live.live_id),
+        )
+        .await
+    }
+
+    #[graphql(complexity = "stat::complexity(limit, child_complexity)")]
+    async fn user_gift_stat(
+        &self,
+        #[graphql(validator(IntGreaterThan(value = "0")))] limit: Option<i32>,
+    ) -> Result<Vec<UserGiftStat>> {
+        query_stat(
+            self.pool().await?,
+            SELECT_USER_GIFT_STAT,
+            ORDER_TOTAL_VALUE_DESC,
+            &self.live.live_id,
+            stat::limit(limit)?,
+            UserGiftStat::from_row,
+        )
+        .await
+    }
+
+    #[graphql(complexity = "stat::complexity(limit, child_complexity)")]
+    async fn user_comment_stat(
+        &self,
+        #[graphql(validator(IntGreaterThan(value = "0")))] limit: Option<i32>,
+    ) -> Result<Vec<UserCommentStat>> {
+        query_stat(
+            self.pool().await?,
+            SELECT_USER_COMMENT_STAT,
+            ORDER_COMMENT_COUNT_DESC,
+            &self.live.live_id,
+            stat::limit(limit)?,
+            UserCommentStat::from_row,
+        )
+        .await
+    }
+}
+
+// 某个用户在这个主播所有直播里的数据，`live_id`不为空时只查询这场直播
+#[derive(Clone, Debug)]
+pub struct UserActivity {
+    user_id: i64,
+    pool: Pool<RusqliteConnectionManager>,
+}
+
+impl UserActivity {
+    #[inline]
+    pub fn new(user_id: i64, pool: Pool<RusqliteConnectionManager>) -> Self {
+        Self { user_id, pool }
+    }
+
+    #[inline]
+    fn filters(&self, column: &'static str, live_id: Option<String>) -> Vec<(&'static str, Value)> {
+        let mut filters = vec![(column, Value::Integer(self.user_id))];
+        if let Some(live_id) = live_id {
+            filters.push((LIVE_ID, Value::Text(live_id)));
+        }
+        filters
+    }
+}
+
+#[Object]
+impl UserActivity {
+    #[inline]
+    async fn user_id(&self) -> i64 {
+        self.user_id
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    #[graphql(
+        guard(TableGuard(table = "Table::Comment")),
+        complexity = "page::complexity(first, last, child_complexity)"
+    )]
+    async fn comments(
+        &self,
+        ctx: &Context<'_>,
+        live_id: Option<String>,
+        after: Option<String>,
+        before: Option<String>,
+        #[graphql(validator(IntGreaterThan(value = "-1")))] first: Option<i32>,
+        #[graphql(validator(IntGreaterThan(value = "-1")))] last: Option<i32>,
+        order_by: Option<OrderBy>,
+    ) -> Result<LiveConnection<Comment>> {
+        let page = Page::new(ctx, after, before, first, last, order_by)?;
+        query_page(
+            self.pool.clone(),
+            page,
+            SELECT_COMMENT,
+            self.filters(USER_ID, live_id),
+        )
+        .await
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    #[graphql(
+        guard(TableGuard(table = "Table::Gift")),
+        complexity = "page::complexity(first, last, child_complexity)"
+    )]
+    async fn gifts(
+        &self,
+        ctx: &Context<'_>,
+        live_id: Option<String>,
+        after: Option<String>,
+        before: Option<String>,
+        #[graphql(validator(IntGreaterThan(value = "-1")))] first: Option<i32>,
+        #[graphql(validator(IntGreaterThan(value = "-1")))] last: Option<i32>,
+        order_by: Option<OrderBy>,
+    ) -> Result<LiveConnection<Gift>> {
+        let page = Page::new(ctx, after, before, first, last, order_by)?;
+        query_page(
+            self.pool.clone(),
+            page,
+            SELECT_GIFT,
+            self.filters(USER_ID, live_id),
+        )
+        .await
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    #[graphql(
+        guard(TableGuard(table = "Table::Follow")),
+        complexity = "page::complexity(first, last, child_complexity)"
+    )]
+    async fn follows(
+        &self,
+        ctx: &Context<'_>,
+        live_id: Option<String>,
+        after: Option<String>,
+        before: Option<String>,
+        #[graphql(validator(IntGreaterThan(value = "-1")))] first: Option<i32>,
+        #[graphql(validator(IntGreaterThan(value = "-1")))] last: Option<i32>,
+        order_by: Option<OrderBy>,
+    ) -> Result<LiveConnection<Follow>> {
+        let page = Page::new(ctx, after, before, first, last, order_by)?;
+        query_page(
+            self.pool.clone(),
+            page,
+            SELECT_FOLLOW,
+            self.filters(USER_ID, live_id),
+        )
+        .await
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    #[graphql(
+        guard(TableGuard(table = "Table::JoinClub")),
+        complexity = "page::complexity(first, last, child_complexity)"
+    )]
+    async fn join_clubs(
+        &self,
+        ctx: &Context<'_>,
+        live_id: Option<String>,
+        after: Option<String>,
+        before: Option<String>,
+        #[graphql(validator(IntGreaterThan(value = "-1")))] first: Option<i32>,
+        #[graphql(validator(IntGreaterThan(value = "-1")))] last: Option<i32>,
+        order_by: Option<OrderBy>,
+    ) -> Result<LiveConnection<JoinClub>> {
+        let page = Page::new(ctx, after, before, first, last, order_by)?;
+        query_page(
+            self.pool.clone(),
+            page,
+            SELECT_JOIN_CLUB,
+            self.filters(FANS_UID, live_id),
+        )
+        .await
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    #[graphql(
+        guard(TableGuard(table = "Table::Redpack")),
+        complexity = "page::complexity(first, last, child_complexity)"
+    )]
+    async fn redpacks(
+        &self,
+        ctx: &Context<'_>,
+        live_id: Option<String>,
+        after: Option<String>,
+        before: Option<String>,
+        #[graphql(validator(IntGreaterThan(value = "-1")))] first: Option<i32>,
+        #[graphql(validator(IntGreaterThan(value = "-1")))] last: Option<i32>,
+        order_by: Option<OrderBy>,
+    ) -> Result<LiveConnection<Redpack>> {
+        let page = Page::new(ctx, after, before, first, last, order_by)?;
+        query_page(
+            self.pool.clone(),
+            page,
+            SELECT_REDPACK,
+            self.filters(SENDER_USER_ID, live_id),
+        )
+        .await
+    }
+}
@@ -0,0 +1,504 @@
+use crate::{row::FromRow, sql::*};
+use anyhow::{anyhow, bail, Error, Result};
+use async_graphql::{
+    connection::{Connection as GraphqlConnection, CursorType, Edge, EmptyFields},
+    Context, Enum, InputObject, SimpleObject,
+};
+use rusqlite::{Connection, Row, ToSql};
+
+pub const DEFAULT_PAGE_SIZE: usize = 100;
+pub const MAX_PAGE_SIZE: usize = 1000;
+
+const TOTAL_COUNT_FIELD: &str = "totalCount";
+
+pub type LiveConnection<T> = GraphqlConnection<RowCursor, T, TotalCount, EmptyFields>;
+
+#[derive(Clone, Copy, Debug, Default, SimpleObject)]
+pub struct TotalCount {
+    pub total_count: i64,
+}
+
+// 列表查询的复杂度为每页的数量乘以每个节点的复杂度
+#[inline]
+pub fn complexity(first: Option<i32>, last: Option<i32>, child_complexity: usize) -> usize {
+    let size = first
+        .or(last)
+        .map(|n| n.max(0) as usize)
+        .unwrap_or(DEFAULT_PAGE_SIZE);
+    size.saturating_mul(child_complexity).saturating_add(1)
+}
+
+#[derive(Clone, Copy, Debug, Enum, Eq, PartialEq)]
+pub enum Direction {
+    Asc,
+    Desc,
+}
+
+impl Default for Direction {
+    #[inline]
+    fn default() -> Self {
+        Direction::Asc
+    }
+}
+
+impl Direction {
+    #[inline]
+    fn reverse(self) -> Self {
+        match self {
+            Direction::Asc => Direction::Desc,
+            Direction::Desc => Direction::Asc,
+        }
+    }
+
+    #[inline]
+    fn as_sql(self) -> &'static str {
+        match self {
+            Direction::Asc => "ASC",
+            Direction::Desc => "DESC",
+        }
+    }
+
+    // 往后翻页的比较符
+    #[inline]
+    fn after_sql(self) -> &'static str {
+        match self {
+            Direction::Asc => ">",
+            Direction::Desc => "<",
+        }
+    }
+}
+
+#[derive(Clone, Debug, Eq, InputObject, PartialEq)]
+pub struct OrderBy {
+    pub column: String,
+    #[graphql(default)]
+    pub direction: Direction,
+}
+
+// 用于分页和排序的列，`index`为该列在SELECT语句里的位置
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Key {
+    pub column: &'static str,
+    pub index: usize,
+}
+
+impl Key {
+    #[inline]
+    pub const fn new(column: &'static str, index: usize) -> Self {
+        Self { column, index }
+    }
+}
+
+// 游标记录了排序的列和方向，用其它排序翻页时报错
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct RowCursor {
+    column: String,
+    direction: Direction,
+    key: i64,
+    rowid: i64,
+}
+
+impl RowCursor {
+    fn check(&self, key: Key, direction: Direction) -> Result<()> {
+        if self.column != key.column || self.direction != direction {
+            bail!(
+                "the cursor is ordered by {} {}, but the query is ordered by {} {}",
+                self.column,
+                self.direction.as_sql(),
+                key.column,
+                direction.as_sql()
+            );
+        }
+
+        Ok(())
+    }
+}
+
+impl CursorType for RowCursor {
+    type Error = Error;
+
+    fn decode_cursor(s: &str) -> Result<Self> {
+        let mut parts = s.split(':');
+        let (column, direction, key, rowid) = match (
+            parts.next(),
+            parts.next(),
+            parts.next(),
+            parts.next(),
+            parts.next(),
+        ) {
+            (Some(column), Some(direction), Some(key), Some(rowid), None) => {
+                (column, direction, key, rowid)
+            }
+            _ => bail!("invalid cursor {}", s),
+        };
+        let direction = match direction {
+            "ASC" => Direction::Asc,
+            "DESC" => Direction::Desc,
+            _ => bail!("invalid cursor {}", s),
+        };
+
+        Ok(Self {
+            column: column.to_string(),
+            direction,
+            key: key.parse()?,
+            rowid: rowid.parse()?,
+        })
+    }
+
+    #[inline]
+    fn encode_cursor(&self) -> String {
+        format!(
+            "{}:{}:{}:{}",
+            self.column,
+            self.direction.as_sql(),
+            self.key,
+            self.rowid
+        )
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Page {
+    after: Option<RowCursor>,
+    before: Option<RowCursor>,
+    order_by: Option<OrderBy>,
+    backward: bool,
+    size: usize,
+    // 多查询一行来判断是否还有下一页
+    limit: i64,
+    total_count: bool,
+}
+
+impl Page {
+    pub fn new(
+        ctx: &Context<'_>,
+        after: Option<String>,
+        before: Option<String>,
+        first: Option<i32>,
+        last: Option<i32>,
+        order_by: Option<OrderBy>,
+    ) -> Result<Self> {
+        let size = match (first, last) {
+            (Some(_), Some(_)) => bail!("first and last can't be used at the same time"),
+            (Some(n), None) | (None, Some(n)) => {
+                if n < 0 {
+                    bail!("the page size is {}, must be greater than -1", n);
+                }
+                if n as usize > MAX_PAGE_SIZE {
+                    bail!(
+                        "the page size is {}, must be less than or equal to {}",
+                        n,
+                        MAX_PAGE_SIZE
+                    );
+                }
+                n as usize
+            }
+            (None, None) => DEFAULT_PAGE_SIZE,
+        };
+
+        Ok(Self {
+            after: after.map(|s| RowCursor::decode_cursor(&s)).transpose()?,
+            before: before.map(|s| RowCursor::decode_cursor(&s)).transpose()?,
+            order_by,
+            backward: last.is_some(),
+            size,
+            limit: size as i64 + 1,
+            total_count: ctx.look_ahead().field(TOTAL_COUNT_FIELD).exists(),
+        })
+    }
+
+    #[inline]
+    pub fn query<'a, T, I>(
+        &'a self,
+        conn: &Connection,
+        sql: String,
+        params: Vec<&'a dyn ToSql>,
+        where_or_and: I,
+    ) -> Result<LiveConnection<T>>
+    where
+        T: FromRow,
+        I: Iterator<Item = &'static str>,
+    {
+        self.query_with(conn, sql, params, where_or_and, T::KEYS, T::from_row)
+    }
+
+    // `keys`是可以排序的列，第一个为默认的排序列
+    fn key(&self, keys: &[Key]) -> Result<(Key, Direction)> {
+        match &self.order_by {
+            Some(order_by) => match keys.iter().find(|k| k.column == order_by.column) {
+                Some(key) => Ok((*key, order_by.direction)),
+                None => bail!(
+                    "can't order by column {}, available columns: {}",
+                    order_by.column,
+                    keys.iter().map(|k| k.column).collect::<Vec<_>>().join(", ")
+                ),
+            },
+            None => Ok((keys[0], Direction::Asc)),
+        }
+    }
+
+    pub fn query_with<'a, T, I, F>(
+        &'a self,
+        conn: &Connection,
+        sql: String,
+        params: Vec<&'a dyn ToSql>,
+        where_or_and: I,
+        keys: &[Key],
+        f: F,
+    ) -> Result<LiveConnection<T>>
+    where
+        I: Iterator<Item = &'static str>,
+        F: FnMut(&Row<'_>) -> rusqlite::Result<T>,
+    {
+        let rows = self.rows(conn, sql, params, where_or_and, keys, f)?;
+        let mut connection = LiveConnection::with_additional_fields(
+            rows.has_previous_page,
+            rows.has_next_page,
+            TotalCount {
+                total_count: rows.total_count,
+            },
+        );
+        connection.append(
+            rows.edges
+                .into_iter()
+                .map(|(cursor, node)| Edge::new(cursor, node)),
+        );
+
+        Ok(connection)
+    }
+
+    fn rows<'a, T, I, F>(
+        &'a self,
+        conn: &Connection,
+        mut sql: String,
+        mut params: Vec<&'a dyn ToSql>,
+        mut where_or_and: I,
+        keys: &[Key],
+        mut f: F,
+    ) -> Result<Rows<T>>
+    where
+        I: Iterator<Item = &'static str>,
+        F: FnMut(&Row<'_>) -> rusqlite::Result<T>,
+    {
+        let (key, direction) = self.key(keys)?;
+        let total_count = if self.total_count {
+            let mut count_sql = SELECT_COUNT.to_string();
+            count_sql += LEFT_PARENTHESES;
+            count_sql += &sql;
+            count_sql += RIGHT_PARENTHESES;
+            count_sql += SEMICOLON;
+            conn.prepare_cached(&count_sql)?
+                .query_row(params.as_slice(), |r| r.get(0))?
+        } else {
+            0
+        };
+
+        // key.column来自白名单，不是用户输入
+        if let Some(after) = &self.after {
+            after.check(key, direction)?;
+            sql += where_or_and.next().unwrap();
+            sql += &format!("\n({}, rowid) {} (?, ?)", key.column, direction.after_sql());
+            params.push(&after.key);
+            params.push(&after.rowid);
+        }
+        if let Some(before) = &self.before {
+            before.check(key, direction)?;
+            sql += where_or_and.next().unwrap();
+            sql += &format!(
+                "\n({}, rowid) {} (?, ?)",
+                key.column,
+                direction.reverse().after_sql()
+            );
+            params.push(&before.key);
+            params.push(&before.rowid);
+        }
+        let order = if self.backward {
+            direction.reverse()
+        } else {
+            direction
+        };
+        sql += &format!("\nORDER BY {0} {1}, rowid {1}", key.column, order.as_sql());
+        sql += LIMIT;
+        sql += SEMICOLON;
+        params.push(&self.limit);
+
+        let mut stmt = conn.prepare_cached(&sql)?;
+        // rowid在SELECT语句的最后一列
+        let rowid_index = stmt.column_count() - 1;
+        let mut edges = stmt
+            .query_map(params.as_slice(), |r| {
+                Ok((
+                    RowCursor {
+                        column: key.column.to_string(),
+                        direction,
+                        key: r.get(key.index)?,
+                        rowid: r.get(rowid_index)?,
+                    },
+                    f(r)?,
+                ))
+            })?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        let has_more = edges.len() > self.size;
+        edges.truncate(self.size);
+        let (has_previous_page, has_next_page) = if self.backward {
+            edges.reverse();
+            (has_more, self.before.is_some())
+        } else {
+            (self.after.is_some(), has_more)
+        };
+
+        Ok(Rows {
+            edges,
+            has_previous_page,
+            has_next_page,
+            total_count,
+        })
+    }
+}
+
+// 一页查询的结果，`edges`已经按排序方向排好
+#[derive(Clone, Debug)]
+struct Rows<T> {
+    edges: Vec<(RowCursor, T)>,
+    has_previous_page: bool,
+    has_next_page: bool,
+    total_count: i64,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::iter;
+
+    const KEYS: [Key; 1] = [Key::new("send_time", 0)];
+
+    fn page(
+        after: Option<&str>,
+        before: Option<&str>,
+        first: Option<usize>,
+        last: Option<usize>,
+    ) -> Result<Page> {
+        let size = first.or(last).unwrap_or(DEFAULT_PAGE_SIZE);
+        Ok(Page {
+            after: after.map(RowCursor::decode_cursor).transpose()?,
+            before: before.map(RowCursor::decode_cursor).transpose()?,
+            order_by: None,
+            backward: last.is_some(),
+            size,
+            limit: size as i64 + 1,
+            total_count: false,
+        })
+    }
+
+    // send_time为10到50，rowid为1到5
+    fn conn() -> Result<Connection> {
+        let conn = Connection::open_in_memory()?;
+        conn.execute_batch(
+            "CREATE TABLE comment (send_time INTEGER NOT NULL);
+INSERT INTO comment (send_time) VALUES (10), (20), (30), (40), (50);",
+        )?;
+
+        Ok(conn)
+    }
+
+    fn query(conn: &Connection, page: &Page) -> Result<Rows<i64>> {
+        page.rows(
+            conn,
+            "SELECT send_time, rowid FROM comment".to_string(),
+            Vec::new(),
+            iter::once(WHERE).chain(iter::repeat(AND)),
+            &KEYS,
+            |r| r.get(0),
+        )
+    }
+
+    fn nodes(rows: &Rows<i64>) -> Vec<i64> {
+        rows.edges.iter().map(|(_, node)| *node).collect()
+    }
+
+    #[test]
+    fn test_cursor() -> Result<()> {
+        let cursor = RowCursor::decode_cursor("send_time:ASC:30:3")?;
+        assert_eq!(
+            cursor,
+            RowCursor {
+                column: "send_time".to_string(),
+                direction: Direction::Asc,
+                key: 30,
+                rowid: 3
+            }
+        );
+        assert_eq!(RowCursor::decode_cursor(&cursor.encode_cursor())?, cursor);
+        assert!(RowCursor::decode_cursor("30:3").is_err());
+        assert!(RowCursor::decode_cursor("send_time:UP:30:3").is_err());
+        assert!(RowCursor::decode_cursor("send_time:ASC:a:3").is_err());
+        assert!(RowCursor::decode_cursor("send_time:ASC:30:3:1").is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_mismatched_cursor() -> Result<()> {
+        let conn = conn()?;
+        let mut page = page(Some("save_time:ASC:30:3"), None, Some(2), None)?;
+        assert!(query(&conn, &page).is_err());
+
+        page.after = Some(RowCursor::decode_cursor("send_time:DESC:30:3")?);
+        assert!(query(&conn, &page).is_err());
+
+        page.after = None;
+        page.before = Some(RowCursor::decode_cursor("send_time:DESC:30:3")?);
+        assert!(query(&conn, &page).is_err());
+
+        page.before = Some(RowCursor::decode_cursor("send_time:ASC:30:3")?);
+        assert_eq!(nodes(&query(&conn, &page)?), vec![10, 20]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_forward() -> Result<()> {
+        let conn = conn()?;
+        let first = query(&conn, &page(None, None, Some(2), None)?)?;
+        assert_eq!(nodes(&first), vec![10, 20]);
+        assert!(!first.has_previous_page);
+        assert!(first.has_next_page);
+
+        let after = first.edges.last().unwrap().0.encode_cursor();
+        let second = query(&conn, &page(Some(&after), None, Some(2), None)?)?;
+        assert_eq!(nodes(&second), vec![30, 40]);
+        assert!(second.has_previous_page);
+        assert!(second.has_next_page);
+
+        let after = second.edges.last().unwrap().0.encode_cursor();
+        let third = query(&conn, &page(Some(&after), None, Some(2), None)?)?;
+        assert_eq!(nodes(&third), vec![50]);
+        assert!(third.has_previous_page);
+        assert!(!third.has_next_page);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_backward() -> Result<()> {
+        let conn = conn()?;
+        let last = query(&conn, &page(None, None, None, Some(2))?)?;
+        assert_eq!(nodes(&last), vec![40, 50]);
+        assert!(last.has_previous_page);
+        assert!(!last.has_next_page);
+
+        let before = last.edges.first().unwrap().0.encode_cursor();
+        let second = query(&conn, &page(None, Some(&before), None, Some(2))?)?;
+        assert_eq!(nodes(&second), vec![20, 30]);
+        assert!(second.has_previous_page);
+        assert!(second.has_next_page);
+
+        let before = second.edges.first().unwrap().0.encode_cursor();
+        let third = query(&conn, &page(None, Some(&before), None, Some(2))?)?;
+        assert_eq!(nodes(&third), vec![10]);
+        assert!(!third.has_previous_page);
+        assert!(third.has_next_page);
+
+        Ok(())
+    }
+}
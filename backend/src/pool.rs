@@ -1,5 +1,6 @@
 // code from https://github.com/LawnGnome/bb8-rusqlite/blob/main/src/lib.rs
 
+use acfunlivedata_common::database::BUSY_TIMEOUT;
 use async_trait::async_trait;
 use bb8::ManageConnection;
 use rusqlite::{Connection as SqliteConn, OpenFlags};
@@ -58,7 +59,10 @@ impl ManageConnection for RusqliteConnectionManager {
         // means we won't inadvertantly block this task for any length of time,
         // since rusqlite is inherently synchronous.
         Ok(tokio::task::spawn_blocking(move || {
-            rusqlite::Connection::open_with_flags(&options.path, options.flags)
+            let conn = rusqlite::Connection::open_with_flags(&options.path, options.flags)?;
+            // 数据中心写入或者checkpoint时等待而不是返回SQLITE_BUSY
+            conn.busy_timeout(BUSY_TIMEOUT)?;
+            Ok::<_, rusqlite::Error>(conn)
         })
         .await??)
     }
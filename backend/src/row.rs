@@ -0,0 +1,374 @@
+use crate::page::Key;
+use acfunlivedata_common::data::*;
+use rusqlite::Row;
+use std::sync::Arc;
+
+pub trait FromRow: Sized {
+    // 可以用于分页和排序的列，第一个为默认的时间列
+    const KEYS: &'static [Key];
+
+    fn from_row(r: &Row<'_>) -> rusqlite::Result<Self>;
+}
+
+// `index`为`user_id`所在的列，之后依次为`nickname`、`avatar`、`medal_uper_uid`、`medal_name`、`medal_level`和`manager`
+#[inline]
+pub fn user_info(r: &Row<'_>, index: usize) -> rusqlite::Result<Option<UserInfo>> {
+    let medal = match r.get::<_, Option<i64>>(index + 3)? {
+        Some(uper_uid) => Some(MedalInfo {
+            uper_uid,
+            name: r.get(index + 4)?,
+            level: r.get(index + 5)?,
+        }),
+        None => None,
+    };
+    match r.get::<_, Option<i64>>(index)? {
+        Some(user_id) => Ok(Some(UserInfo {
+            user_id,
+            nickname: r.get(index + 1)?,
+            avatar: r.get(index + 2)?,
+            medal,
+            manager: r.get(index + 6)?,
+        })),
+        None => Ok(None),
+    }
+}
+
+// `index`为`user_id`所在的列，用户信息之后依次为`live_id`和`enable_jump_peer_live_room`
+#[inline]
+pub fn author_chat_player_info(
+    r: &Row<'_>,
+    index: usize,
+) -> rusqlite::Result<Option<AuthorChatPlayerInfo>> {
+    match r.get::<_, Option<String>>(index + 7)? {
+        Some(live_id) => Ok(Some(AuthorChatPlayerInfo {
+            player_info: user_info(r, index)?,
+            live_id,
+            enable_jump_peer_live_room: r.get::<_, Option<bool>>(index + 8)?.unwrap_or_default(),
+        })),
+        None => Ok(None),
+    }
+}
+
+#[inline]
+fn live_type(r: &Row<'_>, index: usize) -> rusqlite::Result<Option<LiveType>> {
+    match r.get::<_, Option<i32>>(index)? {
+        Some(id) => Ok(Some(LiveType {
+            id,
+            name: r.get(index + 1)?,
+            category_id: r.get(index + 2)?,
+            category_name: r.get(index + 3)?,
+        })),
+        None => Ok(None),
+    }
+}
+
+impl FromRow for Live {
+    const KEYS: &'static [Key] = &[Key::new("start_time", 4)];
+
+    fn from_row(r: &Row<'_>) -> rusqlite::Result<Self> {
+        Ok(Live {
+            live_id: Arc::new(r.get(0)?),
+            liver_uid: r.get(1)?,
+            nickname: r.get(2)?,
+            stream_name: r.get(3)?,
+            start_time: r.get(4)?,
+            title: r.get(5)?,
+            live_type: live_type(r, 6)?,
+            portrait: r.get(10)?,
+            panoramic: r.get(11)?,
+            disable_danmaku_show: r.get(12)?,
+            duration: r.get(13)?,
+            like_count: r.get(14)?,
+            watch_count: r.get(15)?,
+        })
+    }
+}
+
+// 不包含`liver_info`、`title`和`summary`
+impl FromRow for LiveInfo {
+    const KEYS: &'static [Key] = &[Key::new("start_time", 3)];
+
+    fn from_row(r: &Row<'_>) -> rusqlite::Result<Self> {
+        Ok(LiveInfo {
+            live_id: Arc::new(r.get(0)?),
+            liver_uid: r.get(1)?,
+            liver_info: None,
+            stream_name: r.get(2)?,
+            start_time: r.get(3)?,
+            title: None,
+            cover: r.get(4)?,
+            live_type: live_type(r, 5)?,
+            has_fans_club: r.get(9)?,
+            portrait: r.get(10)?,
+            panoramic: r.get(11)?,
+            disable_danmaku_show: r.get(12)?,
+            paid_show_user_buy_status: r.get(13)?,
+            summary: None,
+        })
+    }
+}
+
+impl FromRow for Title {
+    const KEYS: &'static [Key] = &[Key::new("save_time", 1)];
+
+    fn from_row(r: &Row<'_>) -> rusqlite::Result<Self> {
+        Ok(Title {
+            live_id: Arc::new(r.get(0)?),
+            save_time: r.get(1)?,
+            title: r.get(2)?,
+        })
+    }
+}
+
+impl FromRow for LiverInfo {
+    const KEYS: &'static [Key] = &[
+        Key::new("save_time", 1),
+        Key::new("following_count", 6),
+        Key::new("contribute_count", 7),
+        Key::new("live_begin_fans_count", 8),
+    ];
+
+    fn from_row(r: &Row<'_>) -> rusqlite::Result<Self> {
+        Ok(LiverInfo {
+            live_id: Arc::new(r.get(0)?),
+            save_time: r.get(1)?,
+            liver_uid: r.get(2)?,
+            nickname: r.get(3)?,
+            avatar: r.get(4)?,
+            avatar_frame: r.get(5)?,
+            following_count: r.get(6)?,
+            contribute_count: r.get(7)?,
+            live_begin_fans_count: r.get(8)?,
+            live_end_fans_count: r.get(9)?,
+            signature: r.get(10)?,
+            verified_text: r.get(11)?,
+            is_join_up_college: r.get(12)?,
+            medal_name: r.get(13)?,
+            live_begin_medal_count: r.get(14)?,
+            live_end_medal_count: r.get(15)?,
+        })
+    }
+}
+
+impl FromRow for Summary {
+    const KEYS: &'static [Key] = &[Key::new("save_time", 1), Key::new("duration", 2)];
+
+    fn from_row(r: &Row<'_>) -> rusqlite::Result<Self> {
+        Ok(Summary {
+            live_id: Arc::new(r.get(0)?),
+            save_time: r.get(1)?,
+            duration: r.get(2)?,
+            like_count: r.get(3)?,
+            watch_total_count: r.get(4)?,
+            watch_online_max_count: r.get(5)?,
+            banana_count: r.get(6)?,
+        })
+    }
+}
+
+impl FromRow for Comment {
+    const KEYS: &'static [Key] = &[Key::new("send_time", 1)];
+
+    fn from_row(r: &Row<'_>) -> rusqlite::Result<Self> {
+        Ok(Comment {
+            live_id: Arc::new(r.get(0)?),
+            send_time: r.get(1)?,
+            user_info: user_info(r, 2)?,
+            content: r.get(9)?,
+        })
+    }
+}
+
+impl FromRow for Follow {
+    const KEYS: &'static [Key] = &[Key::new("send_time", 1)];
+
+    fn from_row(r: &Row<'_>) -> rusqlite::Result<Self> {
+        Ok(Follow {
+            live_id: Arc::new(r.get(0)?),
+            send_time: r.get(1)?,
+            user_info: user_info(r, 2)?,
+        })
+    }
+}
+
+impl FromRow for Gift {
+    const KEYS: &'static [Key] = &[
+        Key::new("send_time", 1),
+        Key::new("gift_id", 9),
+        Key::new("count", 10),
+        Key::new("combo", 11),
+        Key::new("value", 12),
+    ];
+
+    fn from_row(r: &Row<'_>) -> rusqlite::Result<Self> {
+        Ok(Gift {
+            live_id: Arc::new(r.get(0)?),
+            send_time: r.get(1)?,
+            user_info: user_info(r, 2)?,
+            gift_id: r.get(9)?,
+            count: r.get(10)?,
+            combo: r.get(11)?,
+            value: r.get(12)?,
+            combo_id: r.get(13)?,
+            slot_display_duration: r.get(14)?,
+            expire_duration: r.get(15)?,
+            draw_gift_info: r.get(16)?,
+        })
+    }
+}
+
+impl FromRow for JoinClub {
+    const KEYS: &'static [Key] = &[Key::new("join_time", 1)];
+
+    fn from_row(r: &Row<'_>) -> rusqlite::Result<Self> {
+        let fans_info = match r.get::<_, Option<i64>>(2)? {
+            Some(user_id) => Some(AcFunUserInfo {
+                user_id,
+                nickname: r.get(3)?,
+            }),
+            None => None,
+        };
+        let uper_info = match r.get::<_, Option<i64>>(4)? {
+            Some(user_id) => Some(AcFunUserInfo {
+                user_id,
+                nickname: r.get(5)?,
+            }),
+            None => None,
+        };
+
+        Ok(JoinClub {
+            live_id: Arc::new(r.get(0)?),
+            join_time: r.get(1)?,
+            fans_info,
+            uper_info,
+        })
+    }
+}
+
+impl FromRow for WatchingCount {
+    const KEYS: &'static [Key] = &[Key::new("save_time", 1)];
+
+    fn from_row(r: &Row<'_>) -> rusqlite::Result<Self> {
+        Ok(WatchingCount {
+            live_id: Arc::new(r.get(0)?),
+            save_time: r.get(1)?,
+            watching_count: r.get(2)?,
+        })
+    }
+}
+
+impl FromRow for Redpack {
+    const KEYS: &'static [Key] = &[
+        Key::new("save_time", 2),
+        Key::new("amount", 10),
+        Key::new("grab_begin_time", 13),
+        Key::new("settle_begin_time", 14),
+    ];
+
+    fn from_row(r: &Row<'_>) -> rusqlite::Result<Self> {
+        Ok(Redpack {
+            redpack_id: r.get(0)?,
+            live_id: Arc::new(r.get(1)?),
+            save_time: r.get(2)?,
+            sender_info: user_info(r, 3)?,
+            amount: r.get(10)?,
+            redpack_biz_unit: r.get(11)?,
+            get_token_latest_time: r.get(12)?,
+            grab_begin_time: r.get(13)?,
+            settle_begin_time: r.get(14)?,
+        })
+    }
+}
+
+impl FromRow for ChatCall {
+    const KEYS: &'static [Key] = &[Key::new("call_time", 2)];
+
+    fn from_row(r: &Row<'_>) -> rusqlite::Result<Self> {
+        Ok(ChatCall {
+            chat_id: r.get(0)?,
+            live_id: r.get(1)?,
+            call_time: r.get(2)?,
+        })
+    }
+}
+
+impl FromRow for ChatReady {
+    const KEYS: &'static [Key] = &[Key::new("save_time", 2)];
+
+    fn from_row(r: &Row<'_>) -> rusqlite::Result<Self> {
+        Ok(ChatReady {
+            chat_id: r.get(0)?,
+            live_id: Arc::new(r.get(1)?),
+            save_time: r.get(2)?,
+            guest_info: user_info(r, 3)?,
+            media_type: r.get(10)?,
+        })
+    }
+}
+
+impl FromRow for ChatEnd {
+    const KEYS: &'static [Key] = &[Key::new("save_time", 2)];
+
+    fn from_row(r: &Row<'_>) -> rusqlite::Result<Self> {
+        Ok(ChatEnd {
+            chat_id: r.get(0)?,
+            live_id: Arc::new(r.get(1)?),
+            save_time: r.get(2)?,
+            end_type: r.get(3)?,
+        })
+    }
+}
+
+impl FromRow for AuthorChatCall {
+    const KEYS: &'static [Key] = &[Key::new("call_time", 11)];
+
+    fn from_row(r: &Row<'_>) -> rusqlite::Result<Self> {
+        Ok(AuthorChatCall {
+            author_chat_id: r.get(0)?,
+            live_id: Arc::new(r.get(1)?),
+            inviter_info: author_chat_player_info(r, 2)?,
+            call_time: r.get(11)?,
+        })
+    }
+}
+
+impl FromRow for AuthorChatReady {
+    const KEYS: &'static [Key] = &[Key::new("save_time", 2)];
+
+    fn from_row(r: &Row<'_>) -> rusqlite::Result<Self> {
+        Ok(AuthorChatReady {
+            author_chat_id: r.get(0)?,
+            live_id: Arc::new(r.get(1)?),
+            save_time: r.get(2)?,
+            inviter_info: author_chat_player_info(r, 3)?,
+            invitee_info: author_chat_player_info(r, 12)?,
+        })
+    }
+}
+
+impl FromRow for AuthorChatEnd {
+    const KEYS: &'static [Key] = &[Key::new("save_time", 2)];
+
+    fn from_row(r: &Row<'_>) -> rusqlite::Result<Self> {
+        Ok(AuthorChatEnd {
+            author_chat_id: r.get(0)?,
+            live_id: Arc::new(r.get(1)?),
+            save_time: r.get(2)?,
+            end_type: r.get(3)?,
+            end_live_id: r.get(4)?,
+        })
+    }
+}
+
+impl FromRow for AuthorChatChangeSoundConfig {
+    const KEYS: &'static [Key] = &[Key::new("save_time", 2)];
+
+    fn from_row(r: &Row<'_>) -> rusqlite::Result<Self> {
+        Ok(AuthorChatChangeSoundConfig {
+            author_chat_id: r.get(0)?,
+            live_id: Arc::new(r.get(1)?),
+            save_time: r.get(2)?,
+            sound_config_change_type: r.get(3)?,
+        })
+    }
+}
@@ -0,0 +1,94 @@
+use anyhow::{bail, Result};
+use async_graphql::{Context, Enum, Guard, InputObject, SimpleObject};
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+
+// 受限token可以访问的表，`Chat`和`AuthorChat`包含对应的所有表
+#[derive(Clone, Copy, Debug, Deserialize, Enum, Eq, Hash, PartialEq, Serialize)]
+pub enum Table {
+    LiveInfo,
+    Title,
+    LiverInfo,
+    Summary,
+    Comment,
+    Follow,
+    Gift,
+    JoinClub,
+    WatchingCount,
+    Redpack,
+    Chat,
+    AuthorChat,
+}
+
+// 只用于主播的token，没有Scope的token可以访问所有表和下载数据库
+#[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq, Serialize, SimpleObject)]
+pub struct Scope {
+    pub tables: Vec<Table>,
+    pub download: bool,
+}
+
+impl Scope {
+    #[inline]
+    pub fn contains(&self, table: Table) -> bool {
+        self.tables.contains(&table)
+    }
+}
+
+#[derive(Clone, Debug, Default, InputObject)]
+pub struct ScopeInput {
+    pub tables: Vec<Table>,
+    #[graphql(default)]
+    pub download: bool,
+}
+
+impl From<ScopeInput> for Scope {
+    #[inline]
+    fn from(input: ScopeInput) -> Self {
+        let mut tables = input.tables;
+        tables.sort_unstable_by_key(|t| *t as u8);
+        tables.dedup();
+        Self {
+            tables,
+            download: input.download,
+        }
+    }
+}
+
+#[inline]
+pub fn has_table(ctx: &Context<'_>, table: Table) -> bool {
+    match ctx.data_opt::<Scope>() {
+        Some(scope) => scope.contains(table),
+        None => true,
+    }
+}
+
+#[inline]
+pub fn check_table(ctx: &Context<'_>, table: Table) -> Result<()> {
+    if !has_table(ctx, table) {
+        bail!("the token doesn't have the permission to read {:?}", table);
+    }
+
+    Ok(())
+}
+
+#[inline]
+pub fn check_unscoped(ctx: &Context<'_>) -> Result<()> {
+    if ctx.data_opt::<Scope>().is_some() {
+        bail!("the token is a scoped token");
+    }
+
+    Ok(())
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct TableGuard {
+    pub table: Table,
+}
+
+#[async_trait]
+impl Guard for TableGuard {
+    #[inline]
+    async fn check(&self, ctx: &Context<'_>) -> async_graphql::Result<()> {
+        check_table(ctx, self.table).map_err(|e| e.to_string().into())
+    }
+}
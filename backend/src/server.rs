@@ -1,14 +1,29 @@
-use crate::{auth::Token, config::User, download::Download, model::QueryRoot};
+use crate::{
+    auth::Token,
+    config::{User, CONFIG},
+    download::{Download, DownloadError, TempDirFull},
+    export::export_handler,
+    extension::QueryLimiter,
+    limit::{Limited, LIMITER},
+    model::QueryRoot,
+    mutation::MutationRoot,
+    scope::Scope,
+};
+use anyhow::Error;
 use async_graphql::{
     extensions::Logger,
     http::{playground_source, GraphQLPlaygroundConfig},
-    EmptyMutation, EmptySubscription, Request as GraphqlRequest, Response as GraphqlResponse,
-    Schema,
+    EmptySubscription, Request as GraphqlRequest, Response as GraphqlResponse, Schema,
 };
 use axum::{
-    extract, handler::get, handler::Handler, http::StatusCode, response, service,
-    AddExtensionLayer, Router,
+    body::Body,
+    extract,
+    handler::get,
+    handler::Handler,
+    http::{HeaderMap, Response, StatusCode},
+    response, service, AddExtensionLayer, Router,
 };
+use hyper::body::HttpBody;
 use std::{convert::Infallible, time::Duration};
 use tower::{
     limit::concurrency::ConcurrencyLimitLayer,
@@ -22,17 +37,63 @@ use tower_http::{
 };
 
 const REQUEST_TIMEOUT: Duration = Duration::from_secs(20);
+const DOWNLOAD_TIMEOUT: Duration = Duration::from_secs(30 * 60);
 const CONCURRENCY_LIMIT: usize = 50;
 const HTTP2_KEEP_ALIVE_INTERVAL: Duration = Duration::from_secs(10);
 const HTTP2_KEEP_ALIVE_TIMEOUT: Duration = Duration::from_secs(20);
 const TCP_KEEPALIVE: Duration = Duration::from_secs(30);
 
-type LiveSchema = Schema<QueryRoot, EmptyMutation, EmptySubscription>;
+type LiveSchema = Schema<QueryRoot, MutationRoot, EmptySubscription>;
 
 pub async fn graphql_server() {
     let schema = schema();
     //println!("{}", schema.sdl());
 
+    // 准备下载的数据库可能需要很长时间，单独设置超时
+    let download = Router::new()
+        .route(
+            "/download",
+            service::get(Download.then(|r| async {
+                match r {
+                    Ok((temp, h)) => {
+                        let resp = ServeFile::new(temp.path()).call(()).await?;
+                        let (mut parts, body) = resp.into_parts();
+                        parts.headers.extend(h);
+                        // 响应结束或者客户端断开后删除临时文件
+                        let stream = futures::stream::unfold(
+                            (Box::pin(body), temp),
+                            |(mut body, temp)| async move {
+                                body.data().await.map(|chunk| (chunk, (body, temp)))
+                            },
+                        );
+                        Ok(Response::from_parts(parts, Body::wrap_stream(stream)))
+                    }
+                    Err(e) => {
+                        log::error!("failed to prepare downloading database: {}", e);
+                        Err(e)
+                    }
+                }
+            }))
+            .handle_error(|e: Error| {
+                Ok::<_, Infallible>(if let Some(limited) = e.downcast_ref::<Limited>() {
+                    limited.response()
+                } else if let Some(full) = e.downcast_ref::<TempDirFull>() {
+                    full.response()
+                } else if let Some(e) = e.downcast_ref::<DownloadError>() {
+                    e.response()
+                } else {
+                    (
+                        StatusCode::INTERNAL_SERVER_ERROR,
+                        HeaderMap::new(),
+                        String::new(),
+                    )
+                })
+            }),
+        )
+        .layer(RequireAuthorizationLayer::custom(Token))
+        .layer(TimeoutLayer::new(DOWNLOAD_TIMEOUT))
+        .handle_error(timeout_error);
+
     let app = Router::new()
         .route(
             "/",
@@ -43,33 +104,11 @@ pub async fn graphql_server() {
             ),
         )
         .or(Router::new()
-            .route(
-                "/download",
-                service::get(Download.then(|r| async {
-                    match r {
-                        Ok((p, h)) => {
-                            let mut resp = ServeFile::new(p).call(()).await?;
-                            resp.headers_mut().extend(h);
-                            Ok(resp)
-                        }
-                        Err(e) => {
-                            log::error!("failed to prepare downloading database: {}", e);
-                            Err(e)
-                        }
-                    }
-                }))
-                .handle_error(|_| Ok::<_, Infallible>(StatusCode::INTERNAL_SERVER_ERROR)),
-            )
+            .route("/export", get(export_handler))
             .layer(RequireAuthorizationLayer::custom(Token)))
         .layer(TimeoutLayer::new(REQUEST_TIMEOUT))
-        .handle_error(|e: BoxError| {
-            log::warn!("server receiving a request from client is timeout");
-            if e.is::<Elapsed>() {
-                Ok::<_, Infallible>(StatusCode::REQUEST_TIMEOUT)
-            } else {
-                Ok::<_, Infallible>(StatusCode::INTERNAL_SERVER_ERROR)
-            }
-        })
+        .handle_error(timeout_error)
+        .or(download)
         .layer(LoadShedLayer::new())
         .handle_error(|e: BoxError| {
             log::warn!("server is overloaded");
@@ -92,10 +131,21 @@ pub async fn graphql_server() {
         .expect("failed to serve server");
 }
 
+#[inline]
+fn timeout_error(e: BoxError) -> Result<StatusCode, Infallible> {
+    if e.is::<Elapsed>() {
+        log::warn!("server receiving a request from client is timeout");
+        Ok(StatusCode::REQUEST_TIMEOUT)
+    } else {
+        Ok(StatusCode::INTERNAL_SERVER_ERROR)
+    }
+}
+
 #[inline]
 fn schema() -> LiveSchema {
-    Schema::build(QueryRoot, EmptyMutation, EmptySubscription)
+    Schema::build(QueryRoot, MutationRoot, EmptySubscription)
         .extension(Logger)
+        .extension(QueryLimiter)
         .finish()
 }
 
@@ -108,8 +158,21 @@ async fn graphql_playground() -> impl response::IntoResponse {
 async fn graphql_handler(
     schema: extract::Extension<LiveSchema>,
     user: extract::Extension<User>,
+    scope: Option<extract::Extension<Scope>>,
     req: extract::Json<GraphqlRequest>,
-) -> response::Json<GraphqlResponse> {
-    let req = req.0.data(user.0);
-    schema.execute(req).await.into()
+) -> Result<response::Json<GraphqlResponse>, (StatusCode, HeaderMap, String)> {
+    let limit = CONFIG
+        .get()
+        .expect("failed to get CONFIG")
+        .read()
+        .await
+        .rate_limit(user.0);
+    // 请求结束后才释放并发数
+    let _permit = LIMITER.acquire(user.0, limit).map_err(|e| e.response())?;
+    let mut req = req.0.data(user.0);
+    if let Some(scope) = scope {
+        req = req.data(scope.0);
+    }
+
+    Ok(schema.execute(req).await.into())
 }
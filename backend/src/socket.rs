@@ -1,19 +1,54 @@
 use crate::config::CONFIG;
-use acfunlivedata_common::message::{
-    BackendMessage, DataCenterMessage, MessageSocket, BACKEND_SOCKET,
+use acfunlivedata_common::{
+    client::build_client,
+    message::{
+        BackendMessage, BackendReply, DataCenterMessage, DataCenterReply, MessageError,
+        MessageSocket, BACKEND_SOCKET,
+    },
 };
+use anyhow::{bail, Result};
+use cached::proc_macro::cached;
 use once_cell::sync::OnceCell;
 
 pub static DATA_SOCKET: OnceCell<MessageSocket<&str, DataCenterMessage>> = OnceCell::new();
 
+// 主播由admin添加或删除时同步到数据中心
 pub async fn send_data_message(message: &DataCenterMessage) {
-    if let Err(e) = DATA_SOCKET
+    match DATA_SOCKET
         .get()
         .expect("failed to get DATA_SOCKET")
-        .send(message)
+        .request::<DataCenterReply>(message)
         .await
     {
-        log::error!("failed to send {:?} to data center: {}", message, e);
+        Ok(reply) => log::info!("data center replied {:?} to {:?}", reply, message),
+        Err(e) => log::error!("failed to send {:?} to data center: {}", message, e),
+    }
+}
+
+// 主播正在记录数据的直播的live_id，数据中心没有回复时才调用AcFun API
+#[cached(size = 100, time = 10, result = true)]
+pub async fn recording_live(liver_uid: i64) -> Result<Option<String>> {
+    match DATA_SOCKET
+        .get()
+        .expect("failed to get DATA_SOCKET")
+        .request(&DataCenterMessage::RecordingLive(liver_uid))
+        .await
+    {
+        Ok(DataCenterReply::RecordingLive(live_id)) => Ok(live_id),
+        Ok(reply) => bail!("unexpected reply from data center: {:?}", reply),
+        Err(e) => {
+            log::warn!(
+                "[{}] failed to ask data center for the recording live, fall back to AcFun API: {}",
+                liver_uid,
+                e
+            );
+            let api_client = build_client().await?;
+            let info = api_client.get_user_live_info(liver_uid).await?;
+            Ok(info
+                .live_data
+                .map(|data| data.live_id)
+                .filter(|live_id| !live_id.is_empty()))
+        }
     }
 }
 
@@ -24,21 +59,27 @@ pub async fn message(password: String) {
     loop {
         if let Err(e) = server
             .listen(|m| async move {
+                let liver_uid = match m {
+                    BackendMessage::AddLiver(liver_uid)
+                    | BackendMessage::DeleteLiver(liver_uid) => liver_uid,
+                };
+                if liver_uid <= 0 {
+                    return Err(MessageError::InvalidLiverUid(liver_uid));
+                }
                 let mut config = CONFIG.get().expect("failed to get CONFIG").write().await;
-                match m {
-                    BackendMessage::AddLiver(liver_uid) => {
-                        if let Err(e) = config.add_liver(liver_uid, true).await {
-                            log::warn!("add liver error: {}", e);
-                        }
-                    }
-                    BackendMessage::DeleteLiver(liver_uid) => {
-                        if let Err(e) = config.delete_liver(liver_uid, true).await {
-                            log::warn!("delete liver error: {}", e);
-                        }
-                    }
+                let reply = match m {
+                    BackendMessage::AddLiver(liver_uid) => config
+                        .add_liver(liver_uid, true)
+                        .await
+                        .map(|info| BackendReply::AddLiver(info.exist, info.token)),
+                    BackendMessage::DeleteLiver(liver_uid) => config
+                        .delete_liver(liver_uid, true)
+                        .await
+                        .map(|info| BackendReply::DeleteLiver(info.exist)),
                 }
-                config.save_config().await?;
-                Ok(())
+                .map_err(MessageError::internal)?;
+                config.save_config().await.map_err(MessageError::internal)?;
+                Ok(reply)
             })
             .await
         {
@@ -5,10 +5,21 @@ pub const AND: &str = r"
 AND";
 pub const OR: &str = r"
 OR";
+pub const NOT: &str = r"
+NOT";
 pub const LEFT_PARENTHESES: &str = r"
 (";
 pub const RIGHT_PARENTHESES: &str = r"
 )";
+pub const LIMIT: &str = r"
+LIMIT ?";
+
+pub const DELETE_FROM: &str = r"DELETE FROM ";
+pub const VACUUM_INTO: &str = r"VACUUM INTO ?;";
+pub const VACUUM: &str = r"VACUUM;";
+pub const JOURNAL_MODE_DELETE: &str = r"PRAGMA journal_mode = DELETE;";
+
+pub const SELECT_COUNT: &str = r"SELECT COUNT(*) FROM";
 
 pub const LIVE_ID: &str = r"
 live_id = ?";
@@ -16,6 +27,20 @@ pub const LIVER_UID: &str = r"
 liver_uid = ?";
 pub const USER_ID: &str = r"
 user_id = ?";
+pub const SENDER_USER_ID: &str = r"
+sender_user_id = ?";
+pub const GUEST_USER_ID: &str = r"
+guest_user_id = ?";
+pub const FANS_UID: &str = r"
+fans_uid = ?";
+pub const INVITER_USER_ID: &str = r"
+inviter_user_id = ?";
+pub const INVITER_OR_INVITEE_USER_ID: &str = r"
+? IN (inviter_user_id, invitee_user_id)";
+pub const CHAT_ID: &str = r"
+chat_id = ?";
+pub const AUTHOR_CHAT_ID: &str = r"
+author_chat_id = ?";
 
 pub const START_TIME_START: &str = r"
 start_time >= ?";
@@ -33,16 +58,32 @@ pub const JOIN_TIME_START: &str = r"
 join_time >= ?";
 pub const JOIN_TIME_END: &str = r"
 join_time <= ?";
+pub const CALL_TIME_START: &str = r"
+call_time >= ?";
+pub const CALL_TIME_END: &str = r"
+call_time <= ?";
 
 pub const GIFT_ID: &str = r"
 gift_id = ?";
+pub const LIVE_ID_IN: &str = r"
+live_id IN (";
+pub const ORDER_SAVE_TIME_ASC: &str = r"
+ORDER BY save_time ASC, rowid ASC";
 pub const ORDER_SAVE_TIME_DESC: &str = r"
 ORDER BY save_time DESC";
+pub const USER_ID_NOT_NULL: &str = r"
+user_id IS NOT NULL";
+pub const GROUP_BY_USER_ID: &str = r"
+GROUP BY user_id";
+pub const ORDER_TOTAL_VALUE_DESC: &str = r"
+ORDER BY total_value DESC, user_id ASC";
+pub const ORDER_COMMENT_COUNT_DESC: &str = r"
+ORDER BY comment_count DESC, user_id ASC";
 //pub const LIMIT_ONE: &str = r"
 //LIMIT 1";
 
 pub const SELECT_LIVE: &str = r"SELECT
-live_id, liver_uid, nickname, stream_name, start_time, title, live_type_id, live_type_name, live_type_category_id, live_type_category_name, portrait, panoramic, disable_danmaku_show, duration, like_count, watch_count
+live_id, liver_uid, nickname, stream_name, start_time, title, live_type_id, live_type_name, live_type_category_id, live_type_category_name, portrait, panoramic, disable_danmaku_show, duration, like_count, watch_count, rowid
 FROM live";
 
 pub const SELECT_GIFT_INFO: &str = r"SELECT
@@ -50,37 +91,135 @@ id, save_time, gift_id, gift_name, ar_live_name, pay_wallet_type, gift_price, we
 FROM gift_info";
 
 pub const SELECT_LIVE_INFO: &str = r"SELECT
-live_id, liver_uid, stream_name, start_time, cover, live_type_id, live_type_name, live_type_category_id, live_type_category_name, has_fans_club, portrait, panoramic, disable_danmaku_show, paid_show_user_buy_status
+live_id, liver_uid, stream_name, start_time, cover, live_type_id, live_type_name, live_type_category_id, live_type_category_name, has_fans_club, portrait, panoramic, disable_danmaku_show, paid_show_user_buy_status, rowid
 FROM live_info";
 
 pub const SELECT_TITLE: &str = r"SELECT
-live_id, save_time, title
+live_id, save_time, title, rowid
 FROM title";
 
 pub const SELECT_LIVER_INFO: &str = r"SELECT
-live_id, save_time, liver_uid, nickname, avatar, avatar_frame, following_count, contribute_count, live_begin_fans_count, live_end_fans_count, signature, verified_text, is_join_up_college, medal_name, live_begin_medal_count, live_end_medal_count
+live_id, save_time, liver_uid, nickname, avatar, avatar_frame, following_count, contribute_count, live_begin_fans_count, live_end_fans_count, signature, verified_text, is_join_up_college, medal_name, live_begin_medal_count, live_end_medal_count, rowid
 FROM liver_info";
 
 pub const SELECT_SUMMARY: &str = r"SELECT
-live_id, save_time, duration, like_count, watch_total_count, watch_online_max_count, banana_count
+live_id, save_time, duration, like_count, watch_total_count, watch_online_max_count, banana_count, rowid
 FROM summary";
 
 pub const SELECT_COMMENT: &str = r"SELECT
-live_id, send_time, user_id, nickname, avatar, medal_uper_uid, medal_name, medal_level, manager, content
+live_id, send_time, user_id, nickname, avatar, medal_uper_uid, medal_name, medal_level, manager, content, rowid
+FROM comment";
+
+// 用户信息为最近一次发送时的信息
+pub const SELECT_USER_GIFT_STAT: &str = r"SELECT
+user_id, nickname, avatar, medal_uper_uid, medal_name, medal_level, manager, MAX(send_time) AS last_send_time, COUNT(*) AS times, SUM(count) AS gift_count, SUM(value) AS total_value
+FROM gift";
+
+pub const SELECT_USER_COMMENT_STAT: &str = r"SELECT
+user_id, nickname, avatar, medal_uper_uid, medal_name, medal_level, manager, MAX(send_time) AS last_send_time, COUNT(*) AS comment_count
 FROM comment";
 
+pub const SELECT_LIVE_START_TIME: &str = r"SELECT
+start_time
+FROM live_info
+WHERE
+live_id = ?;";
+
+// 参数依次为直播开始时间、区间长度（毫秒）和`live_id`，`value`为区间内礼物总价值或最大在线观众数
+pub const SELECT_COMMENT_TIMELINE: &str = r"SELECT
+(send_time - ?1) / ?2 AS bucket, COUNT(*), NULL
+FROM comment
+WHERE
+live_id = ?3
+AND
+send_time >= ?1
+GROUP BY bucket
+ORDER BY bucket ASC;";
+
+pub const SELECT_GIFT_TIMELINE: &str = r"SELECT
+(send_time - ?1) / ?2 AS bucket, COUNT(*), SUM(value)
+FROM gift
+WHERE
+live_id = ?3
+AND
+send_time >= ?1
+GROUP BY bucket
+ORDER BY bucket ASC;";
+
+pub const SELECT_FOLLOW_TIMELINE: &str = r"SELECT
+(send_time - ?1) / ?2 AS bucket, COUNT(*), NULL
+FROM follow
+WHERE
+live_id = ?3
+AND
+send_time >= ?1
+GROUP BY bucket
+ORDER BY bucket ASC;";
+
+pub const SELECT_JOIN_CLUB_TIMELINE: &str = r"SELECT
+(join_time - ?1) / ?2 AS bucket, COUNT(*), NULL
+FROM join_club
+WHERE
+live_id = ?3
+AND
+join_time >= ?1
+GROUP BY bucket
+ORDER BY bucket ASC;";
+
+pub const SELECT_WATCHING_COUNT_TIMELINE: &str = r"SELECT
+(save_time - ?1) / ?2 AS bucket, COUNT(*), MAX(watching_count)
+FROM watching_count
+WHERE
+live_id = ?3
+AND
+save_time >= ?1
+GROUP BY bucket
+ORDER BY bucket ASC;";
+
 pub const SELECT_FOLLOW: &str = r"SELECT
-live_id, send_time, user_id, nickname, avatar, medal_uper_uid, medal_name, medal_level, manager
+live_id, send_time, user_id, nickname, avatar, medal_uper_uid, medal_name, medal_level, manager, rowid
 FROM follow";
 
 pub const SELECT_GIFT: &str = r"SELECT
-live_id, send_time, user_id, nickname, avatar, medal_uper_uid, medal_name, medal_level, manager, gift_id, count, combo, value, combo_id, slot_display_duration, expire_duration, draw_gift_info
+live_id, send_time, user_id, nickname, avatar, medal_uper_uid, medal_name, medal_level, manager, gift_id, count, combo, value, combo_id, slot_display_duration, expire_duration, draw_gift_info, rowid
 FROM gift";
 
 pub const SELECT_JOIN_CLUB: &str = r"SELECT
-live_id, join_time, fans_uid, fans_nickname, uper_uid, uper_nickname
+live_id, join_time, fans_uid, fans_nickname, uper_uid, uper_nickname, rowid
 FROM join_club";
 
 pub const SELECT_WATCHING_COUNT: &str = r"SELECT
-live_id, save_time, watching_count
+live_id, save_time, watching_count, rowid
 FROM watching_count";
+
+pub const SELECT_REDPACK: &str = r"SELECT
+redpack_id, live_id, save_time, sender_user_id, sender_nickname, sender_avatar, sender_medal_uper_uid, sender_medal_name, sender_medal_level, sender_manager, amount, redpack_biz_unit, get_token_latest_time, grab_begin_time, settle_begin_time, rowid
+FROM redpack";
+
+pub const SELECT_CHAT_CALL: &str = r"SELECT
+chat_id, live_id, call_time, rowid
+FROM chat_call";
+
+pub const SELECT_CHAT_READY: &str = r"SELECT
+chat_id, live_id, save_time, guest_user_id, guest_nickname, guest_avatar, guest_medal_uper_uid, guest_medal_name, guest_medal_level, guest_manager, media_type, rowid
+FROM chat_ready";
+
+pub const SELECT_CHAT_END: &str = r"SELECT
+chat_id, live_id, save_time, end_type, rowid
+FROM chat_end";
+
+pub const SELECT_AUTHOR_CHAT_CALL: &str = r"SELECT
+author_chat_id, live_id, inviter_user_id, inviter_nickname, inviter_avatar, inviter_medal_uper_uid, inviter_medal_name, inviter_medal_level, inviter_manager, inviter_live_id, inviter_enable_jump_peer_live_room, call_time, rowid
+FROM author_chat_call";
+
+pub const SELECT_AUTHOR_CHAT_READY: &str = r"SELECT
+author_chat_id, live_id, save_time, inviter_user_id, inviter_nickname, inviter_avatar, inviter_medal_uper_uid, inviter_medal_name, inviter_medal_level, inviter_manager, inviter_live_id, inviter_enable_jump_peer_live_room, invitee_user_id, invitee_nickname, invitee_avatar, invitee_medal_uper_uid, invitee_medal_name, invitee_medal_level, invitee_manager, invitee_live_id, invitee_enable_jump_peer_live_room, rowid
+FROM author_chat_ready";
+
+pub const SELECT_AUTHOR_CHAT_END: &str = r"SELECT
+author_chat_id, live_id, save_time, end_type, end_live_id, rowid
+FROM author_chat_end";
+
+pub const SELECT_AUTHOR_CHAT_CHANGE_SOUND_CONFIG: &str = r"SELECT
+author_chat_id, live_id, save_time, sound_config_change_type, rowid
+FROM author_chat_change_sound_config";
@@ -0,0 +1,284 @@
+use crate::{
+    object::UserActivity,
+    page::{DEFAULT_PAGE_SIZE, MAX_PAGE_SIZE},
+    pool::RusqliteConnectionManager,
+    row::user_info,
+    scope::Table,
+};
+use acfunlivedata_common::data::UserInfo;
+use anyhow::{bail, Result};
+use async_graphql::{Enum, SimpleObject};
+use bb8::Pool;
+use rusqlite::Row;
+
+#[inline]
+pub fn limit(limit: Option<i32>) -> Result<i64> {
+    match limit {
+        Some(limit) if limit as usize > MAX_PAGE_SIZE => bail!(
+            "the limit is {}, must be less than or equal to {}",
+            limit,
+            MAX_PAGE_SIZE
+        ),
+        Some(limit) => Ok(limit as i64),
+        None => Ok(DEFAULT_PAGE_SIZE as i64),
+    }
+}
+
+#[inline]
+pub fn complexity(limit: Option<i32>, child_complexity: usize) -> usize {
+    let limit = limit
+        .map(|n| n.max(0) as usize)
+        .unwrap_or(DEFAULT_PAGE_SIZE);
+    limit.saturating_mul(child_complexity).saturating_add(1)
+}
+
+// 每个用户的礼物统计，`times`为送礼的次数，`gift_count`为礼物的总数，`activity`为该用户在这个主播所有直播里的数据
+#[derive(Clone, Debug, SimpleObject)]
+pub struct UserGiftStat {
+    pub user_info: UserInfo,
+    pub last_send_time: i64,
+    pub times: i64,
+    pub gift_count: i64,
+    pub total_value: i64,
+    pub activity: UserActivity,
+}
+
+impl UserGiftStat {
+    pub fn from_row(r: &Row<'_>, pool: &Pool<RusqliteConnectionManager>) -> rusqlite::Result<Self> {
+        // 查询时已经排除了`user_id`为NULL的行
+        let user_info = user_info(r, 0)?.unwrap_or_default();
+        Ok(UserGiftStat {
+            activity: UserActivity::new(user_info.user_id, pool.clone()),
+            user_info,
+            last_send_time: r.get(7)?,
+            times: r.get(8)?,
+            gift_count: r.get(9)?,
+            total_value: r.get(10)?,
+        })
+    }
+}
+
+#[derive(Clone, Debug, SimpleObject)]
+pub struct UserCommentStat {
+    pub user_info: UserInfo,
+    pub last_send_time: i64,
+    pub comment_count: i64,
+    pub activity: UserActivity,
+}
+
+impl UserCommentStat {
+    pub fn from_row(r: &Row<'_>, pool: &Pool<RusqliteConnectionManager>) -> rusqlite::Result<Self> {
+        let user_info = user_info(r, 0)?.unwrap_or_default();
+        Ok(UserCommentStat {
+            activity: UserActivity::new(user_info.user_id, pool.clone()),
+            user_info,
+            last_send_time: r.get(7)?,
+            comment_count: r.get(8)?,
+        })
+    }
+}
+
+#[derive(Clone, Copy, Debug, Enum, Eq, Hash, PartialEq)]
+pub enum TimelineKind {
+    Comment,
+    Gift,
+    Follow,
+    JoinClub,
+    WatchingCount,
+}
+
+impl TimelineKind {
+    pub const ALL: [TimelineKind; 5] = [
+        TimelineKind::Comment,
+        TimelineKind::Gift,
+        TimelineKind::Follow,
+        TimelineKind::JoinClub,
+        TimelineKind::WatchingCount,
+    ];
+
+    #[inline]
+    pub fn table(self) -> Table {
+        match self {
+            TimelineKind::Comment => Table::Comment,
+            TimelineKind::Gift => Table::Gift,
+            TimelineKind::Follow => Table::Follow,
+            TimelineKind::JoinClub => Table::JoinClub,
+            TimelineKind::WatchingCount => Table::WatchingCount,
+        }
+    }
+}
+
+// 按每种数据100个区间估算
+const TIMELINE_BUCKETS: usize = 100;
+
+#[inline]
+pub fn timeline_complexity(kinds: &Option<Vec<TimelineKind>>, child_complexity: usize) -> usize {
+    let kinds = kinds.as_ref().map_or(TimelineKind::ALL.len(), |k| k.len());
+    kinds
+        .saturating_mul(TIMELINE_BUCKETS)
+        .saturating_mul(child_complexity)
+        .saturating_add(1)
+}
+
+// `index`从0开始，到最后一个有数据的区间为止，没有数据的区间`count`为0，`value`为null
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, SimpleObject)]
+pub struct Bucket {
+    pub index: i64,
+    pub start_time: i64,
+    pub count: i64,
+    pub value: Option<i64>,
+}
+
+impl Bucket {
+    pub fn from_row(
+        r: &Row<'_>,
+        live_start_time: i64,
+        bucket_millis: i64,
+    ) -> rusqlite::Result<Self> {
+        let index: i64 = r.get(0)?;
+        Ok(Bucket {
+            index,
+            start_time: live_start_time + index * bucket_millis,
+            count: r.get(1)?,
+            value: r.get(2)?,
+        })
+    }
+
+    #[inline]
+    fn empty(index: i64, live_start_time: i64, bucket_millis: i64) -> Self {
+        Bucket {
+            index,
+            start_time: live_start_time + index * bucket_millis,
+            count: 0,
+            value: None,
+        }
+    }
+}
+
+// 补上没有数据的区间，`buckets`要按`index`升序排列
+pub fn fill_buckets(buckets: Vec<Bucket>, live_start_time: i64, bucket_millis: i64) -> Vec<Bucket> {
+    let len = buckets.last().map_or(0, |b| b.index + 1);
+    let mut buckets = buckets.into_iter().peekable();
+    (0..len)
+        .map(|index| {
+            buckets
+                .next_if(|b| b.index == index)
+                .unwrap_or_else(|| Bucket::empty(index, live_start_time, bucket_millis))
+        })
+        .collect()
+}
+
+// 没有请求的种类为null
+#[derive(Clone, Debug, Default, Eq, PartialEq, SimpleObject)]
+pub struct Timeline {
+    pub live_id: String,
+    pub start_time: i64,
+    pub bucket_seconds: i64,
+    pub comment: Option<Vec<Bucket>>,
+    pub gift: Option<Vec<Bucket>>,
+    pub follow: Option<Vec<Bucket>>,
+    pub join_club: Option<Vec<Bucket>>,
+    pub watching_count: Option<Vec<Bucket>>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sql::{SELECT_COMMENT_TIMELINE, SELECT_GIFT_TIMELINE};
+    use rusqlite::{params, Connection};
+
+    const LIVE_START_TIME: i64 = 1000;
+    const BUCKET_MILLIS: i64 = 10;
+
+    fn timeline(conn: &Connection, sql: &str) -> Result<Vec<Bucket>> {
+        let buckets = conn
+            .prepare(sql)?
+            .query_map(params![LIVE_START_TIME, BUCKET_MILLIS, "live"], |r| {
+                Bucket::from_row(r, LIVE_START_TIME, BUCKET_MILLIS)
+            })?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+
+        Ok(fill_buckets(buckets, LIVE_START_TIME, BUCKET_MILLIS))
+    }
+
+    #[test]
+    fn test_timeline() -> Result<()> {
+        let conn = Connection::open_in_memory()?;
+        conn.execute_batch(
+            "CREATE TABLE comment (live_id TEXT, send_time INTEGER);
+            CREATE TABLE gift (live_id TEXT, send_time INTEGER, value INTEGER);",
+        )?;
+        // 开播前的数据不算在第一个区间里
+        for (live_id, send_time) in [
+            ("live", 991),
+            ("live", 1000),
+            ("live", 1009),
+            ("live", 1035),
+            ("other", 1015),
+        ] {
+            let _ = conn.execute(
+                "INSERT INTO comment (live_id, send_time) VALUES (?, ?)",
+                params![live_id, send_time],
+            )?;
+            let _ = conn.execute(
+                "INSERT INTO gift (live_id, send_time, value) VALUES (?, ?, ?)",
+                params![live_id, send_time, send_time - 990],
+            )?;
+        }
+
+        let bucket = |index, count, value| Bucket {
+            index,
+            start_time: LIVE_START_TIME + index * BUCKET_MILLIS,
+            count,
+            value,
+        };
+        assert_eq!(
+            timeline(&conn, SELECT_COMMENT_TIMELINE)?,
+            vec![
+                bucket(0, 2, None),
+                bucket(1, 0, None),
+                bucket(2, 0, None),
+                bucket(3, 1, None),
+            ]
+        );
+        assert_eq!(
+            timeline(&conn, SELECT_GIFT_TIMELINE)?,
+            vec![
+                bucket(0, 2, Some(29)),
+                bucket(1, 0, None),
+                bucket(2, 0, None),
+                bucket(3, 1, Some(45)),
+            ]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_fill_buckets() {
+        assert!(fill_buckets(Vec::new(), LIVE_START_TIME, BUCKET_MILLIS).is_empty());
+        let buckets = fill_buckets(
+            vec![Bucket {
+                index: 2,
+                start_time: 1020,
+                count: 3,
+                value: Some(1),
+            }],
+            LIVE_START_TIME,
+            BUCKET_MILLIS,
+        );
+        assert_eq!(
+            buckets,
+            vec![
+                Bucket::empty(0, LIVE_START_TIME, BUCKET_MILLIS),
+                Bucket::empty(1, LIVE_START_TIME, BUCKET_MILLIS),
+                Bucket {
+                    index: 2,
+                    start_time: 1020,
+                    count: 3,
+                    value: Some(1),
+                },
+            ]
+        );
+    }
+}
@@ -27,7 +27,7 @@ pub type Manager = bool;
 pub type LiveId = Arc<String>;
 
 #[inline]
-fn unix_time() -> i64 {
+pub(crate) fn unix_time() -> i64 {
     chrono::Utc::now().timestamp_millis()
 }
 
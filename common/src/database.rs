@@ -1,10 +1,12 @@
 use once_cell::sync::Lazy;
-use std::path::PathBuf;
+use std::{path::PathBuf, time::Duration};
 
 const DATABASE_DIR: &str = "database";
 const LIVERS_DIR: &str = "livers";
 pub const ACFUN_LIVE_DATABASE_NAME: &str = "acfunlive.db";
 pub const GIFT_DATABASE_NAME: &str = "gift.db";
+// 数据库被锁时的最长等待时间，数据中心和后端都要设置
+pub const BUSY_TIMEOUT: Duration = Duration::from_secs(10);
 
 pub static DATABASE_DIRECTORY: Lazy<PathBuf> = Lazy::new(|| {
     let mut path = crate::DIRECTORY_PATH.clone();
@@ -1,34 +1,74 @@
-use crate::socket::Socket;
-use anyhow::Result;
+use crate::{
+    data::unix_time,
+    socket::{receive, Connection, Socket},
+};
+use anyhow::{bail, Result};
 use asynchronous_codec::{Framed, LengthCodec};
 use encon::Password;
-use futures::StreamExt;
-use once_cell::sync::Lazy;
+use futures::{SinkExt, StreamExt};
+use interprocess::nonblocking::local_socket::LocalSocketStream;
 use serde::{de::DeserializeOwned, Deserialize, Serialize};
-use std::{future::Future, io, marker::PhantomData, time::Duration};
+use std::{
+    collections::HashMap,
+    future::Future,
+    io,
+    marker::PhantomData,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+    time::Duration,
+};
 use tokio::time;
 
 pub const DATA_CENTER_SOCKET: &str = "/tmp/acfunlivedata.sock";
 pub const BACKEND_SOCKET: &str = "/tmp/acfunlivedata_backend.sock";
-pub const TOOL_SOCKET: &str = "/tmp/acfunlivedata_tool.sock";
-pub const TOOL_PASSWORD: &str = "acfunlivedata-tool";
+
+// 不兼容的协议修改需要增加版本号，新增消息种类只需要增加capability
+pub const PROTOCOL_VERSION: u32 = 2;
+// 能兼容的对方最低版本
+pub const MIN_PROTOCOL_VERSION: u32 = 2;
 
 const TIMEOUT: Duration = Duration::from_secs(5);
+// 请求的时间戳和本地时间相差超过这个值（毫秒）就拒绝
+const REPLAY_WINDOW: i64 = 30_000;
 
-pub static TOOL_SOCKET_CLIENT: Lazy<MessageSocket<&str, ToolMessage>> =
-    Lazy::new(|| MessageSocket::new_client(TOOL_SOCKET, TOOL_PASSWORD));
+static REQUEST_ID: AtomicU64 = AtomicU64::new(0);
 
-#[inline]
-pub async fn send_tool_message(message: &ToolMessage) {
-    if let Err(e) = TOOL_SOCKET_CLIENT.send(message).await {
-        log::error!("failed to send {:?} to tool: {}", message, e);
-    }
+// 消息enum的variant序列化为序号，新增的variant只能加在最后
+pub trait Message {
+    const CAPABILITIES: &'static [&'static str];
+
+    fn capability(&self) -> &'static str;
 }
 
 #[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
 pub enum DataCenterMessage {
-    AddLiver(i64, bool),
-    DeleteLiver(i64, bool),
+    AddLiver(i64),
+    DeleteLiver(i64),
+    RecordingLive(i64),
+}
+
+impl Message for DataCenterMessage {
+    const CAPABILITIES: &'static [&'static str] = &["AddLiver", "DeleteLiver", "RecordingLive"];
+
+    #[inline]
+    fn capability(&self) -> &'static str {
+        match self {
+            Self::AddLiver(_) => "AddLiver",
+            Self::DeleteLiver(_) => "DeleteLiver",
+            Self::RecordingLive(_) => "RecordingLive",
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub enum DataCenterReply {
+    // 主播之前是否已经在配置里
+    AddLiver(bool),
+    DeleteLiver(bool),
+    // 正在记录数据的直播的live_id
+    RecordingLive(Option<String>),
 }
 
 #[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
@@ -37,18 +77,122 @@ pub enum BackendMessage {
     DeleteLiver(i64),
 }
 
+impl Message for BackendMessage {
+    const CAPABILITIES: &'static [&'static str] = &["AddLiver", "DeleteLiver"];
+
+    #[inline]
+    fn capability(&self) -> &'static str {
+        match self {
+            Self::AddLiver(_) => "AddLiver",
+            Self::DeleteLiver(_) => "DeleteLiver",
+        }
+    }
+}
+
 #[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
-pub enum ToolMessage {
-    DataCenterAddLiver(i64, bool),
-    DataCenterDeleteLiver(i64, bool),
-    BackendAddLiver(i64, bool, String),
-    BackendDeleteLiver(i64, bool),
+pub enum BackendReply {
+    // 新添加的主播才有token
+    AddLiver(bool, Option<String>),
+    DeleteLiver(bool),
+}
+
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize, thiserror::Error)]
+pub enum MessageError {
+    #[error("liver uid {0} is less than 1")]
+    InvalidLiverUid(i64),
+    #[error("request {0} was timeout")]
+    Timeout(u64),
+    #[error("the ID of the reply {1} doesn't match the request {0}")]
+    MismatchedId(u64, u64),
+    #[error("{0}")]
+    Internal(String),
+    #[error(
+        "the protocol version {client} of the client is incompatible with the version {server} of the server"
+    )]
+    IncompatibleVersion { server: u32, client: u32 },
+    #[error("the peer doesn't support the message {0}, it may need to be upgraded")]
+    Unsupported(String),
+    #[error("the timestamp {0} of the request is out of the replay window")]
+    Expired(i64),
+    #[error("the nonce {0} of the request has been used")]
+    Replayed(u64),
+}
+
+impl MessageError {
+    #[inline]
+    pub fn internal(e: impl std::fmt::Display) -> Self {
+        Self::Internal(e.to_string())
+    }
+}
+
+// 每个连接先交换Handshake，结构不能修改
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct Handshake {
+    pub version: u32,
+    pub min_version: u32,
+    pub capabilities: Vec<String>,
+}
+
+impl Handshake {
+    #[inline]
+    fn new<M: Message>() -> Self {
+        Self {
+            version: PROTOCOL_VERSION,
+            min_version: MIN_PROTOCOL_VERSION,
+            capabilities: M::CAPABILITIES.iter().map(|c| c.to_string()).collect(),
+        }
+    }
+
+    // 检查对方的Handshake，`is_server`为本地是否为服务端
+    #[inline]
+    fn check(&self, is_server: bool) -> std::result::Result<(), MessageError> {
+        if self.version < MIN_PROTOCOL_VERSION || self.min_version > PROTOCOL_VERSION {
+            let (server, client) = if is_server {
+                (PROTOCOL_VERSION, self.version)
+            } else {
+                (self.version, PROTOCOL_VERSION)
+            };
+            Err(MessageError::IncompatibleVersion { server, client })
+        } else {
+            Ok(())
+        }
+    }
+
+    #[inline]
+    pub fn supports(&self, capability: &str) -> bool {
+        self.capabilities.iter().any(|c| c == capability)
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+struct Request<M> {
+    id: u64,
+    nonce: u64,
+    timestamp: i64,
+    message: M,
+}
+
+// Request的开头部分，无法解析消息时也能读取
+#[derive(Clone, Copy, Debug, Deserialize, Serialize)]
+struct RequestHeader {
+    id: u64,
+    nonce: u64,
+    timestamp: i64,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+struct Reply<R> {
+    id: u64,
+    nonce: u64,
+    result: std::result::Result<R, MessageError>,
 }
 
 #[derive(Clone)]
 pub struct MessageSocket<P, M> {
     password: Password,
     socket: Socket<P>,
+    // 服务端记录重放窗口内用过的nonce
+    nonces: Arc<Mutex<HashMap<u64, i64>>>,
     message: PhantomData<M>,
 }
 
@@ -58,6 +202,7 @@ impl<P, M> MessageSocket<P, M> {
         Self {
             password: Password::new(password),
             socket: Socket::new(path, true),
+            nonces: Arc::new(Mutex::new(HashMap::new())),
             message: PhantomData,
         }
     }
@@ -67,6 +212,7 @@ impl<P, M> MessageSocket<P, M> {
         Self {
             password: Password::new(password),
             socket: Socket::new(path, false),
+            nonces: Arc::new(Mutex::new(HashMap::new())),
             message: PhantomData,
         }
     }
@@ -75,49 +221,198 @@ impl<P, M> MessageSocket<P, M> {
     pub fn is_server(&self) -> bool {
         self.socket.is_server()
     }
+
+    #[inline]
+    fn encrypt<T: Serialize>(&self, data: &T) -> io::Result<Vec<u8>> {
+        self.password
+            .encrypt(bincode::serialize(data).map_err(invalid_data)?)
+            .map_err(invalid_data)
+    }
+
+    #[inline]
+    fn decrypt<T: DeserializeOwned>(&self, data: &[u8]) -> io::Result<T> {
+        bincode::deserialize(&self.password.decrypt(data).map_err(invalid_data)?)
+            .map_err(invalid_data)
+    }
+
+    fn check_replay(&self, header: &RequestHeader) -> std::result::Result<(), MessageError> {
+        let now = unix_time();
+        if (now - header.timestamp).abs() > REPLAY_WINDOW {
+            return Err(MessageError::Expired(header.timestamp));
+        }
+        let mut nonces = self.nonces.lock().map_err(MessageError::internal)?;
+        // 超出窗口的请求会因为时间戳被拒绝，不用再记录它们的nonce
+        nonces.retain(|_, t| now - *t <= REPLAY_WINDOW);
+        if nonces.insert(header.nonce, header.timestamp).is_some() {
+            return Err(MessageError::Replayed(header.nonce));
+        }
+
+        Ok(())
+    }
+
+    #[inline]
+    async fn send<T: Serialize>(&self, conn: &mut Connection, data: &T) -> io::Result<()> {
+        conn.send(self.encrypt(data)?.into()).await
+    }
+}
+
+#[inline]
+fn invalid_data<E>(e: E) -> io::Error
+where
+    E: Into<Box<dyn std::error::Error + Send + Sync>>,
+{
+    io::Error::new(io::ErrorKind::InvalidData, e)
 }
 
 impl<M> MessageSocket<&'static str, M>
 where
-    M: DeserializeOwned,
+    M: Message + DeserializeOwned,
 {
-    #[inline]
-    pub async fn listen<F, Fut>(&self, mut f: F) -> Result<()>
+    // 每个连接先握手再处理一个请求，`f`的结果在同一个连接上回复
+    pub async fn listen<R, F, Fut>(&self, f: F) -> Result<()>
     where
+        R: Serialize,
         F: FnMut(M) -> Fut + Copy,
-        Fut: Future<Output = Result<()>>,
+        Fut: Future<Output = std::result::Result<R, MessageError>>,
     {
+        // 单个连接的错误不能结束监听
         self.socket
             .listen(|conn| async move {
-                let mut framed = Framed::new(conn, LengthCodec);
-                let bytes = match framed.next().await.transpose()? {
-                    Some(b) => b,
-                    None => return Ok(()),
-                };
-                let msg: M = bincode::deserialize(
-                    &self
-                        .password
-                        .decrypt(bytes.as_ref())
-                        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?,
-                )
-                .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
-                f(msg)
-                    .await
-                    .map_err(|e| io::Error::new(io::ErrorKind::Other, e))?;
+                if let Err(e) = self.serve(conn, f).await {
+                    log::warn!("failed to serve a connection: {}", e);
+                }
                 Ok(())
             })
             .await
     }
+
+    async fn serve<R, F, Fut>(&self, conn: LocalSocketStream, mut f: F) -> io::Result<()>
+    where
+        R: Serialize,
+        F: FnMut(M) -> Fut,
+        Fut: Future<Output = std::result::Result<R, MessageError>>,
+    {
+        let mut conn = Framed::new(conn, LengthCodec);
+        let bytes = match receive(&mut conn).await? {
+            Some(b) => b,
+            None => return Ok(()),
+        };
+        let data = self
+            .password
+            .decrypt(bytes.as_ref())
+            .map_err(invalid_data)?;
+        // 旧版本的对方不握手，直接发送请求
+        let handshake: Handshake = match bincode::deserialize(&data) {
+            Ok(handshake) => handshake,
+            Err(e) => {
+                log::warn!("the peer speaks pre-handshake protocol: {}", e);
+                return conn.close().await;
+            }
+        };
+        let check = handshake.check(true);
+        self.send(&mut conn, &check.clone().map(|_| Handshake::new::<M>()))
+            .await?;
+        if let Err(e) = check {
+            log::warn!("rejected a client: {}", e);
+            return conn.close().await;
+        }
+        // 对方不支持要发送的消息时会直接关闭连接
+        let bytes = match receive(&mut conn).await? {
+            Some(b) => b,
+            None => return Ok(()),
+        };
+        let data = self
+            .password
+            .decrypt(bytes.as_ref())
+            .map_err(invalid_data)?;
+        let header: RequestHeader = bincode::deserialize(&data).map_err(invalid_data)?;
+        let result = match self.check_replay(&header) {
+            Ok(()) => match bincode::deserialize::<Request<M>>(&data) {
+                Ok(request) => f(request.message).await,
+                Err(e) => Err(MessageError::Unsupported(e.to_string())),
+            },
+            Err(e) => {
+                log::warn!("rejected a request: {}", e);
+                Err(e)
+            }
+        };
+        let reply = Reply {
+            id: header.id,
+            nonce: header.nonce,
+            result,
+        };
+        self.send(&mut conn, &reply).await?;
+        conn.close().await
+    }
 }
 
 impl<M> MessageSocket<&'static str, M>
 where
-    M: Serialize,
+    M: Message + Serialize,
 {
-    #[inline]
-    pub async fn send(&self, message: &M) -> Result<()> {
-        let msg = self.password.encrypt(bincode::serialize(message)?)?;
-        time::timeout(TIMEOUT, self.socket.write(msg)).await?
+    // 对方返回的错误可以downcast成MessageError
+    pub async fn request<R: DeserializeOwned>(&self, message: &M) -> Result<R> {
+        let id = REQUEST_ID.fetch_add(1, Ordering::Relaxed);
+        match time::timeout(TIMEOUT, self.exchange(id, message)).await {
+            Ok(result) => result,
+            Err(_) => Err(MessageError::Timeout(id).into()),
+        }
+    }
+
+    // 只握手，返回对方的版本和支持的消息种类
+    pub async fn handshake(&self) -> Result<Handshake> {
+        let handshake = async {
+            let mut conn = self.socket.connect().await?;
+            let handshake = self.shake(&mut conn).await?;
+            conn.close().await?;
+            Ok::<_, anyhow::Error>(handshake)
+        };
+        match time::timeout(TIMEOUT, handshake).await {
+            Ok(result) => result,
+            Err(_) => bail!("handshaking was timeout"),
+        }
+    }
+
+    async fn shake(&self, conn: &mut Connection) -> Result<Handshake> {
+        self.send(conn, &Handshake::new::<M>()).await?;
+        let handshake: std::result::Result<Handshake, MessageError> = match receive(conn).await? {
+            Some(b) => self.decrypt(b.as_ref())?,
+            None => bail!(
+                "the connection was closed before handshaking, the peer may speak pre-handshake protocol and need to be upgraded"
+            ),
+        };
+        let handshake = handshake?;
+        handshake.check(false)?;
+
+        Ok(handshake)
+    }
+
+    async fn exchange<R: DeserializeOwned>(&self, id: u64, message: &M) -> Result<R> {
+        let mut conn = self.socket.connect().await?;
+        let handshake = self.shake(&mut conn).await?;
+        let capability = message.capability();
+        if !handshake.supports(capability) {
+            conn.close().await?;
+            bail!(MessageError::Unsupported(capability.to_string()));
+        }
+        let nonce = rand::random();
+        let request = Request {
+            id,
+            nonce,
+            timestamp: unix_time(),
+            message,
+        };
+        self.send(&mut conn, &request).await?;
+        let reply: Reply<R> = match receive(&mut conn).await? {
+            Some(b) => self.decrypt(b.as_ref())?,
+            None => bail!("the connection was closed before replying"),
+        };
+        conn.close().await?;
+        if reply.id != id || reply.nonce != nonce {
+            bail!(MessageError::MismatchedId(id, reply.id));
+        }
+
+        Ok(reply.result?)
     }
 }
 
@@ -136,18 +431,96 @@ mod tests {
         let _ = tokio::spawn(async move {
             server
                 .listen(|m| async move {
-                    assert_eq!(m, DataCenterMessage::AddLiver(100, false));
-                    Ok(())
+                    match m {
+                        DataCenterMessage::AddLiver(100) => Ok(DataCenterReply::AddLiver(false)),
+                        DataCenterMessage::RecordingLive(100) => {
+                            Ok(DataCenterReply::RecordingLive(Some("abc".to_string())))
+                        }
+                        DataCenterMessage::AddLiver(liver_uid) => {
+                            Err(MessageError::InvalidLiverUid(liver_uid))
+                        }
+                        _ => Err(MessageError::internal("unexpected message")),
+                    }
                 })
                 .await
                 .unwrap();
         });
         sleep(Duration::from_secs(2)).await;
-        client
-            .send(&DataCenterMessage::AddLiver(100, false))
+        let handshake = client.handshake().await?;
+        assert_eq!(handshake.version, PROTOCOL_VERSION);
+        assert!(handshake.supports(DataCenterMessage::RecordingLive(100).capability()));
+        assert!(!handshake.supports("Unknown"));
+        // 没有握手的旧版本请求和无法解密的数据都不会结束监听
+        let mut conn = client.socket.connect().await?;
+        let old_request = (0u64, DataCenterMessage::AddLiver(100));
+        client.send(&mut conn, &old_request).await?;
+        assert!(receive(&mut conn).await?.is_none());
+        let mut conn = client.socket.connect().await?;
+        conn.send(b"garbage".to_vec().into()).await?;
+        assert!(receive(&mut conn).await?.is_none());
+        let reply: DataCenterReply = client.request(&DataCenterMessage::AddLiver(100)).await?;
+        assert_eq!(reply, DataCenterReply::AddLiver(false));
+        let reply: DataCenterReply = client
+            .request(&DataCenterMessage::RecordingLive(100))
             .await?;
-        sleep(Duration::from_secs(2)).await;
+        assert_eq!(
+            reply,
+            DataCenterReply::RecordingLive(Some("abc".to_string()))
+        );
+        let err = client
+            .request::<DataCenterReply>(&DataCenterMessage::AddLiver(-1))
+            .await
+            .unwrap_err();
+        assert_eq!(
+            err.downcast_ref::<MessageError>(),
+            Some(&MessageError::InvalidLiverUid(-1))
+        );
 
         Ok(())
     }
+
+    #[test]
+    fn test_handshake_check() {
+        let mut handshake = Handshake::new::<BackendMessage>();
+        assert_eq!(handshake.check(true), Ok(()));
+        handshake.version = MIN_PROTOCOL_VERSION - 1;
+        assert_eq!(
+            handshake.check(true),
+            Err(MessageError::IncompatibleVersion {
+                server: PROTOCOL_VERSION,
+                client: MIN_PROTOCOL_VERSION - 1
+            })
+        );
+        assert_eq!(
+            handshake.check(false),
+            Err(MessageError::IncompatibleVersion {
+                server: MIN_PROTOCOL_VERSION - 1,
+                client: PROTOCOL_VERSION
+            })
+        );
+        handshake.version = PROTOCOL_VERSION + 1;
+        handshake.min_version = PROTOCOL_VERSION + 1;
+        assert!(handshake.check(true).is_err());
+    }
+
+    #[test]
+    fn test_check_replay() {
+        let server: MessageSocket<_, DataCenterMessage> =
+            MessageSocket::new_server(DATA_CENTER_SOCKET, "abcd");
+        let mut header = RequestHeader {
+            id: 0,
+            nonce: 1,
+            timestamp: unix_time(),
+        };
+        assert_eq!(server.check_replay(&header), Ok(()));
+        assert_eq!(server.check_replay(&header), Err(MessageError::Replayed(1)));
+        header.nonce = 2;
+        assert_eq!(server.check_replay(&header), Ok(()));
+        header.nonce = 3;
+        header.timestamp -= REPLAY_WINDOW + 1000;
+        assert_eq!(
+            server.check_replay(&header),
+            Err(MessageError::Expired(header.timestamp))
+        );
+    }
 }
@@ -1,7 +1,8 @@
 use crate::file_exist;
 use anyhow::{bail, Result};
 use asynchronous_codec::{Framed, LengthCodec};
-use futures::{SinkExt, TryStreamExt};
+use bytes::Bytes;
+use futures::{StreamExt, TryStreamExt};
 use interprocess::nonblocking::local_socket::{LocalSocketListener, LocalSocketStream};
 use std::{future::Future, path::Path};
 use tokio::fs;
@@ -54,25 +55,31 @@ impl Socket<&'static str> {
         Ok(())
     }
 
+    // 返回的连接可以多次收发消息
     #[inline]
-    pub(crate) async fn write(&self, message: Vec<u8>) -> Result<()> {
+    pub(crate) async fn connect(&self) -> Result<Connection> {
         if self.is_server {
             bail!("not a client");
         }
         let conn = LocalSocketStream::connect(self.path).await?;
-        let mut framed = Framed::new(conn, LengthCodec);
-        framed.send(message.into()).await?;
-        framed.close().await?;
 
-        Ok(())
+        Ok(Framed::new(conn, LengthCodec))
     }
 }
 
+pub(crate) type Connection = Framed<LocalSocketStream, LengthCodec>;
+
+// 对方关闭连接时返回None
+#[inline]
+pub(crate) async fn receive(conn: &mut Connection) -> std::io::Result<Option<Bytes>> {
+    conn.next().await.transpose()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::message::BACKEND_SOCKET;
-    use futures::StreamExt;
+    use futures::SinkExt;
     use std::time::Duration;
     use tokio::time::sleep;
 
@@ -84,16 +91,21 @@ mod tests {
             server
                 .listen(|conn| async move {
                     let mut framed = Framed::new(conn, LengthCodec);
-                    let msg = framed.next().await.transpose()?.unwrap();
+                    let msg = receive(&mut framed).await?.unwrap();
                     assert_eq!(msg.as_ref(), b"hello, server");
+                    framed.send(b"hello, client".to_vec().into()).await?;
+                    framed.close().await?;
                     Ok(())
                 })
                 .await
                 .unwrap();
         });
         sleep(Duration::from_secs(2)).await;
-        client.write(b"hello, server".to_vec()).await?;
-        sleep(Duration::from_secs(2)).await;
+        let mut conn = client.connect().await?;
+        conn.send(b"hello, server".to_vec().into()).await?;
+        let reply = receive(&mut conn).await?.unwrap();
+        assert_eq!(reply.as_ref(), b"hello, client");
+        conn.close().await?;
 
         Ok(())
     }
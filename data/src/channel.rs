@@ -0,0 +1,342 @@
+use anyhow::{bail, Result};
+use once_cell::sync::Lazy;
+use std::{
+    collections::VecDeque,
+    sync::{
+        atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering},
+        Arc, Mutex, Weak,
+    },
+    time::Duration,
+};
+use tokio::{sync::Notify, time};
+
+pub const DEFAULT_CAPACITY: usize = 10_000;
+const MONITOR_INTERVAL: Duration = Duration::from_secs(60);
+
+// 所有channel的统计，channel关闭后自动移除
+static CHANNELS: Lazy<Mutex<Vec<Weak<ChannelStats>>>> = Lazy::new(|| Mutex::new(Vec::new()));
+
+// 队列满时能丢弃的数据会丢弃队列里最旧的同类数据，其余的数据阻塞发送方等待
+pub trait Overflow {
+    #[inline]
+    fn droppable(&self) -> bool {
+        false
+    }
+}
+
+#[derive(Debug)]
+pub struct ChannelStats {
+    name: String,
+    capacity: usize,
+    depth: AtomicUsize,
+    max_depth: AtomicUsize,
+    dropped: AtomicU64,
+}
+
+impl ChannelStats {
+    #[inline]
+    fn set_depth(&self, depth: usize) {
+        self.depth.store(depth, Ordering::Relaxed);
+        let _ = self.max_depth.fetch_max(depth, Ordering::Relaxed);
+    }
+}
+
+impl std::fmt::Display for ChannelStats {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "channel {}: depth {}/{}, max depth {}, dropped {}",
+            self.name,
+            self.depth.load(Ordering::Relaxed),
+            self.capacity,
+            self.max_depth.load(Ordering::Relaxed),
+            self.dropped.load(Ordering::Relaxed)
+        )
+    }
+}
+
+#[derive(Debug)]
+struct Shared<T> {
+    queue: Mutex<VecDeque<T>>,
+    senders: AtomicUsize,
+    // 接收方已经关闭
+    closed: AtomicBool,
+    // 发送方主动关闭
+    finished: AtomicBool,
+    not_empty: Notify,
+    not_full: Notify,
+    stats: Arc<ChannelStats>,
+}
+
+#[derive(Debug)]
+pub struct Sender<T> {
+    shared: Arc<Shared<T>>,
+}
+
+#[derive(Debug)]
+pub struct Receiver<T> {
+    shared: Arc<Shared<T>>,
+}
+
+pub fn channel<T>(name: impl Into<String>, capacity: usize) -> (Sender<T>, Receiver<T>) {
+    let capacity = capacity.max(1);
+    let stats = Arc::new(ChannelStats {
+        name: name.into(),
+        capacity,
+        depth: AtomicUsize::new(0),
+        max_depth: AtomicUsize::new(0),
+        dropped: AtomicU64::new(0),
+    });
+    CHANNELS
+        .lock()
+        .expect("failed to lock CHANNELS")
+        .push(Arc::downgrade(&stats));
+    let shared = Arc::new(Shared {
+        queue: Mutex::new(VecDeque::with_capacity(capacity.min(DEFAULT_CAPACITY))),
+        senders: AtomicUsize::new(1),
+        closed: AtomicBool::new(false),
+        finished: AtomicBool::new(false),
+        not_empty: Notify::new(),
+        not_full: Notify::new(),
+        stats,
+    });
+
+    (
+        Sender {
+            shared: shared.clone(),
+        },
+        Receiver { shared },
+    )
+}
+
+#[derive(Debug)]
+pub enum TrySendError<T> {
+    Full(T),
+    Closed(T),
+}
+
+impl<T: Overflow> Sender<T> {
+    // 队列满并且数据不能丢弃时返回Full，不会等待
+    pub fn try_send(&self, data: T) -> Result<(), TrySendError<T>> {
+        let shared = &self.shared;
+        let mut queue = shared.queue.lock().expect("failed to lock channel queue");
+        if shared.closed.load(Ordering::Acquire) || shared.finished.load(Ordering::Acquire) {
+            return Err(TrySendError::Closed(data));
+        }
+        if queue.len() >= shared.stats.capacity && data.droppable() {
+            let _ = shared.stats.dropped.fetch_add(1, Ordering::Relaxed);
+            match queue.iter().position(|d| d.droppable()) {
+                Some(i) => {
+                    let _ = queue.remove(i);
+                }
+                // 队列里没有能丢弃的数据时丢弃新的数据
+                None => return Ok(()),
+            }
+        }
+        if queue.len() < shared.stats.capacity {
+            queue.push_back(data);
+            shared.stats.set_depth(queue.len());
+            drop(queue);
+            shared.not_empty.notify_one();
+            return Ok(());
+        }
+
+        Err(TrySendError::Full(data))
+    }
+
+    pub async fn send(&self, mut data: T) -> Result<()> {
+        let shared = &self.shared;
+        loop {
+            // 在检查队列之前注册，避免错过接收方的通知
+            let notified = shared.not_full.notified();
+            match self.try_send(data) {
+                Ok(()) => return Ok(()),
+                Err(TrySendError::Full(d)) => data = d,
+                Err(TrySendError::Closed(_)) => {
+                    if shared.closed.load(Ordering::Acquire) {
+                        bail!("the receiver of channel {} was closed", shared.stats.name);
+                    }
+                    bail!("channel {} was closed", shared.stats.name);
+                }
+            }
+            notified.await;
+        }
+    }
+}
+
+impl<T> Sender<T> {
+    // 用于static的发送方，接收方取完队列里剩下的数据后返回None，之后的发送都会失败
+    pub fn close(&self) {
+        let shared = &self.shared;
+        {
+            let _queue = shared.queue.lock().expect("failed to lock channel queue");
+            shared.finished.store(true, Ordering::Release);
+        }
+        shared.not_empty.notify_one();
+        shared.not_full.notify_waiters();
+    }
+}
+
+impl<T> Clone for Sender<T> {
+    #[inline]
+    fn clone(&self) -> Self {
+        let _ = self.shared.senders.fetch_add(1, Ordering::Relaxed);
+        Self {
+            shared: self.shared.clone(),
+        }
+    }
+}
+
+impl<T> Drop for Sender<T> {
+    fn drop(&mut self) {
+        if self.shared.senders.fetch_sub(1, Ordering::AcqRel) == 1 {
+            self.shared.not_empty.notify_one();
+        }
+    }
+}
+
+impl<T> Receiver<T> {
+    // 所有发送方关闭或者channel被关闭，并且队列为空时返回None
+    pub async fn recv(&mut self) -> Option<T> {
+        let shared = &self.shared;
+        loop {
+            let notified = shared.not_empty.notified();
+            {
+                let mut queue = shared.queue.lock().expect("failed to lock channel queue");
+                if let Some(data) = queue.pop_front() {
+                    shared.stats.set_depth(queue.len());
+                    drop(queue);
+                    shared.not_full.notify_one();
+                    return Some(data);
+                }
+                if shared.senders.load(Ordering::Acquire) == 0
+                    || shared.finished.load(Ordering::Acquire)
+                {
+                    return None;
+                }
+            }
+            notified.await;
+        }
+    }
+
+    // 不能在异步运行时的线程里调用
+    #[inline]
+    pub fn blocking_recv(&mut self) -> Option<T> {
+        futures::executor::block_on(self.recv())
+    }
+}
+
+impl<T> Drop for Receiver<T> {
+    fn drop(&mut self) {
+        self.shared.closed.store(true, Ordering::Release);
+        self.shared.not_full.notify_waiters();
+    }
+}
+
+// 定时记录各个channel的队列长度和丢弃的数量
+pub async fn monitor() {
+    let mut interval = time::interval(MONITOR_INTERVAL);
+    loop {
+        let _ = interval.tick().await;
+        let mut channels = CHANNELS.lock().expect("failed to lock CHANNELS");
+        channels.retain(|stats| stats.strong_count() > 0);
+        for stats in channels.iter().filter_map(Weak::upgrade) {
+            let depth = stats.depth.load(Ordering::Relaxed);
+            if depth * 5 >= stats.capacity * 4 {
+                log::warn!("the writer is falling behind, {}", stats);
+            } else if depth > 0 || stats.dropped.load(Ordering::Relaxed) > 0 {
+                log::info!("{}", stats);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, PartialEq)]
+    enum Data {
+        Sample(i32),
+        Gift(i32),
+    }
+
+    impl Overflow for Data {
+        fn droppable(&self) -> bool {
+            matches!(self, Self::Sample(_))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_overflow() -> Result<()> {
+        let (tx, mut rx) = channel("test", 2);
+        tx.send(Data::Sample(1)).await?;
+        tx.send(Data::Gift(1)).await?;
+        tx.send(Data::Sample(2)).await?;
+        assert_eq!(rx.recv().await, Some(Data::Gift(1)));
+        assert_eq!(rx.recv().await, Some(Data::Sample(2)));
+
+        tx.send(Data::Gift(2)).await?;
+        tx.send(Data::Gift(3)).await?;
+        // 队列里没有能丢弃的数据
+        tx.send(Data::Sample(3)).await?;
+        let tx_ = tx.clone();
+        let handle = tokio::spawn(async move { tx_.send(Data::Gift(4)).await });
+        time::sleep(Duration::from_millis(100)).await;
+        assert_eq!(rx.shared.stats.depth.load(Ordering::Relaxed), 2);
+        assert_eq!(rx.recv().await, Some(Data::Gift(2)));
+        handle.await??;
+        assert_eq!(rx.recv().await, Some(Data::Gift(3)));
+        assert_eq!(rx.recv().await, Some(Data::Gift(4)));
+        assert_eq!(rx.shared.stats.dropped.load(Ordering::Relaxed), 2);
+
+        drop(tx);
+        assert_eq!(rx.recv().await, None);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_close() -> Result<()> {
+        let (tx, mut rx) = channel("test", 1);
+        tx.send(Data::Gift(1)).await?;
+        let tx_ = tx.clone();
+        let handle = tokio::spawn(async move { tx_.send(Data::Gift(2)).await });
+        time::sleep(Duration::from_millis(100)).await;
+        tx.close();
+        // 阻塞的发送方也会失败
+        assert!(handle.await?.is_err());
+        assert!(tx.send(Data::Gift(3)).await.is_err());
+        assert_eq!(rx.recv().await, Some(Data::Gift(1)));
+        assert_eq!(rx.recv().await, None);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_try_send() -> Result<()> {
+        let (tx, mut rx) = channel("test", 1);
+        assert!(tx.try_send(Data::Gift(1)).is_ok());
+        assert!(matches!(
+            tx.try_send(Data::Gift(2)),
+            Err(TrySendError::Full(Data::Gift(2)))
+        ));
+        // 能丢弃的数据不会返回Full
+        assert!(tx.try_send(Data::Sample(1)).is_ok());
+        assert_eq!(rx.recv().await, Some(Data::Gift(1)));
+        tx.close();
+        assert!(matches!(
+            tx.try_send(Data::Gift(3)),
+            Err(TrySendError::Closed(Data::Gift(3)))
+        ));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_closed() {
+        let (tx, rx) = channel::<Data>("test", 1);
+        drop(rx);
+        assert!(tx.send(Data::Gift(1)).await.is_err());
+    }
+}
@@ -1,8 +1,5 @@
-use acfunlivedata_common::{
-    config::Config as CommonConfig,
-    message::{send_tool_message, ToolMessage},
-    DIRECTORY_PATH,
-};
+use crate::channel::DEFAULT_CAPACITY;
+use acfunlivedata_common::{config::Config as CommonConfig, message::MessageError, DIRECTORY_PATH};
 use ahash::AHashSet;
 use once_cell::sync::Lazy;
 use serde::{Deserialize, Serialize};
@@ -22,49 +19,56 @@ pub type LiveConfig = CommonConfig<Config, &'static Path>;
 #[derive(Clone, Debug, Default, Deserialize, Serialize)]
 pub struct Config {
     livers: Livers,
+    // 每个channel的容量，0为默认值
+    #[serde(default)]
+    channel_capacity: usize,
 }
 
 impl Config {
+    #[inline]
+    pub fn channel_capacity(&self) -> usize {
+        if self.channel_capacity == 0 {
+            DEFAULT_CAPACITY
+        } else {
+            self.channel_capacity
+        }
+    }
+
     #[inline]
     pub fn contains(&self, liver_uid: i64) -> bool {
         self.livers.contains(&liver_uid)
     }
 
+    // 返回主播之前是否已经在配置里
     #[inline]
-    pub async fn add_liver(&mut self, liver_uid: i64, tool: bool) {
+    pub fn add_liver(&mut self, liver_uid: i64) -> Result<bool, MessageError> {
         if liver_uid > 0 {
             log::info!("add liver {}", liver_uid);
             if self.livers.insert(liver_uid) {
-                if tool {
-                    send_tool_message(&ToolMessage::DataCenterAddLiver(liver_uid, false)).await;
-                }
+                Ok(false)
             } else {
                 log::warn!("already added liver {} before", liver_uid);
-                if tool {
-                    send_tool_message(&ToolMessage::DataCenterAddLiver(liver_uid, true)).await;
-                }
+                Ok(true)
             }
         } else {
             log::warn!("liver uid {} is less than 1", liver_uid);
+            Err(MessageError::InvalidLiverUid(liver_uid))
         }
     }
 
     #[inline]
-    pub async fn delete_liver(&mut self, liver_uid: i64, tool: bool) {
+    pub fn delete_liver(&mut self, liver_uid: i64) -> Result<bool, MessageError> {
         if liver_uid > 0 {
             log::info!("delete liver {}", liver_uid);
             if self.livers.remove(&liver_uid) {
-                if tool {
-                    send_tool_message(&ToolMessage::DataCenterDeleteLiver(liver_uid, true)).await;
-                }
+                Ok(true)
             } else {
                 log::warn!("liver {} wasn't in config", liver_uid);
-                if tool {
-                    send_tool_message(&ToolMessage::DataCenterDeleteLiver(liver_uid, false)).await;
-                }
+                Ok(false)
             }
         } else {
             log::warn!("liver uid {} is less than 1", liver_uid);
+            Err(MessageError::InvalidLiverUid(liver_uid))
         }
     }
 }
@@ -1,4 +1,8 @@
-use crate::{config::LiveConfig, sqlite::save_data};
+use crate::{
+    channel::{self, Overflow, Receiver, Sender, TrySendError},
+    config::LiveConfig,
+    sqlite::save_data,
+};
 use acfunliveapi::response::{
     Gift as ApiGift, LiveData as ApiLiveData, Summary as ApiSummary, UserInfo, UserLiveInfo,
 };
@@ -7,17 +11,29 @@ use acfunlivedanmaku::{
     client::DanmakuClient,
     danmaku::*,
 };
-use acfunlivedata_common::{client::build_client, data::*, message::DataCenterMessage};
+use acfunlivedata_common::{
+    client::build_client,
+    data::*,
+    message::{DataCenterMessage, DataCenterReply, MessageError},
+};
 use ahash::{AHashMap, AHashSet};
 use anyhow::{Context, Result};
-use futures::StreamExt;
-use once_cell::sync::OnceCell;
+use futures::{FutureExt, StreamExt};
+use once_cell::sync::{Lazy, OnceCell};
 use std::{future::Future, sync::Arc, time::Duration};
-use tokio::{sync::mpsc, time};
+use tokio::{
+    signal::unix::{signal, SignalKind},
+    sync::{oneshot, watch},
+    task::JoinHandle,
+    time,
+};
 
-pub static LIVE_TX: OnceCell<mpsc::UnboundedSender<LiveMessage>> = OnceCell::new();
-pub static ALL_LIVES_TX: OnceCell<mpsc::UnboundedSender<AllLiveData>> = OnceCell::new();
-pub static GIFT_TX: OnceCell<mpsc::UnboundedSender<Vec<ApiGift>>> = OnceCell::new();
+pub static LIVE_TX: OnceCell<Sender<LiveMessage>> = OnceCell::new();
+pub static ALL_LIVES_TX: OnceCell<Sender<AllLiveData>> = OnceCell::new();
+pub static GIFT_TX: OnceCell<Sender<Vec<ApiGift>>> = OnceCell::new();
+// 关闭数据中心时变为true，通知所有弹幕客户端停止
+static SHUTDOWN: Lazy<(watch::Sender<bool>, watch::Receiver<bool>)> =
+    Lazy::new(|| watch::channel(false));
 
 const LIVE_LIST_INTERVAL: Duration = Duration::from_secs(10);
 const RETRY_INTERVAL: Duration = Duration::from_secs(2);
@@ -51,6 +67,16 @@ pub enum LiveData {
     AuthorChatEnd(AuthorChatEnd),
     AuthorChatChangeSoundConfig(AuthorChatChangeSoundConfig),
     Stop,
+    // 数据中心关闭，保存剩下的数据后停止
+    Interrupted,
+}
+
+// 观看人数只保留最新的数据，礼物等其他数据不能丢弃
+impl Overflow for LiveData {
+    #[inline]
+    fn droppable(&self) -> bool {
+        matches!(self, Self::WatchingCount(_))
+    }
 }
 
 #[derive(Clone, Debug)]
@@ -59,22 +85,35 @@ pub enum AllLiveData {
     Summary(LiveId, ApiSummary),
 }
 
-#[derive(Clone, Debug)]
+impl Overflow for AllLiveData {}
+
+impl Overflow for Vec<ApiGift> {}
+
+#[derive(Debug)]
 pub enum LiveMessage {
     LiveList(Vec<UserLiveInfo>),
     StopDanmaku(LiveId),
     StopSummary(LiveId),
-    Command(DataCenterMessage),
+    Command(
+        DataCenterMessage,
+        oneshot::Sender<Result<DataCenterReply, MessageError>>,
+    ),
+    // 所有直播的数据保存完后回复
+    Shutdown(oneshot::Sender<()>),
 }
 
+impl Overflow for LiveMessage {}
+
 #[derive(Clone, Debug)]
 struct LiveMapData {
+    liver_uid: i64,
     title: Option<String>,
-    data_tx: mpsc::UnboundedSender<LiveData>,
+    data_tx: Sender<LiveData>,
 }
 
+// 成功时返回true
 #[inline]
-async fn run_thrice<F, Fut>(name: &str, f: F)
+async fn run_thrice<F, Fut>(name: &str, f: F) -> bool
 where
     F: Fn() -> Fut,
     Fut: Future<Output = Result<()>>,
@@ -83,7 +122,7 @@ where
         if let Err(e) = f().await {
             log::warn!("{} error: {}: {}", name, e, e.root_cause());
         } else {
-            return;
+            return true;
         }
         if i == 2 {
             log::error!("failed to run {} thrice", name);
@@ -91,13 +130,26 @@ where
             time::sleep(RETRY_INTERVAL).await;
         }
     }
+
+    false
+}
+
+// 关闭数据中心时返回，调用之前已经关闭的情况下立即返回
+async fn wait_shutdown() {
+    let mut shutdown_rx = SHUTDOWN.1.clone();
+    // clone的Receiver会把当前值当作已经看过，所以要先检查当前值
+    while !*shutdown_rx.borrow() {
+        if shutdown_rx.changed().await.is_err() {
+            unreachable!("SHUTDOWN sender was dropped");
+        }
+    }
 }
 
 #[derive(Clone, Debug)]
 struct Liver {
     live_id: LiveId,
     liver_uid: i64,
-    data_tx: Option<mpsc::UnboundedSender<LiveData>>,
+    data_tx: Option<Sender<LiveData>>,
 }
 
 impl Liver {
@@ -110,17 +162,37 @@ impl Liver {
         }
     }
 
+    // channel满了的时候会等待
     #[inline]
-    fn send_message<T>(&self, tx: &mpsc::UnboundedSender<T>, message: T) {
-        if let Err(e) = tx.send(message) {
+    async fn send_message<T: Overflow>(&self, tx: &Sender<T>, message: T) {
+        if let Err(e) = tx.send(message).await {
             log::error!("{} failed to send message through channel: {}", self, e);
         }
     }
 
+    // 不等待channel，满了的时候在新的任务里发送，用于不能阻塞的all_danmaku()
+    fn spawn_message<T>(&self, tx: &Sender<T>, message: T)
+    where
+        T: Overflow + Send + 'static,
+    {
+        match tx.try_send(message) {
+            Ok(()) => {}
+            Err(TrySendError::Full(message)) => {
+                log::warn!("{} channel is full, sending message in a new task", self);
+                let liver = self.clone();
+                let tx = tx.clone();
+                let _ = tokio::spawn(async move { liver.send_message(&tx, message).await });
+            }
+            Err(TrySendError::Closed(_)) => {
+                log::error!("{} failed to send message through channel: closed", self);
+            }
+        }
+    }
+
     #[inline]
-    fn send_data_message(&self, data: LiveData) {
+    async fn send_data_message(&self, data: LiveData) {
         if let Some(tx) = &self.data_tx {
-            self.send_message(tx, data);
+            self.send_message(tx, data).await;
         } else {
             unreachable!("{} data_tx is None", self);
         }
@@ -135,7 +207,7 @@ impl Liver {
             .await
             .with_context(|| format!("{} failed to get gift list", self))?;
         let gift_tx = GIFT_TX.get().expect("failed to get GIFT_TX");
-        self.send_message(gift_tx, list.data.gift_list);
+        self.send_message(gift_tx, list.data.gift_list).await;
 
         Ok(())
     }
@@ -146,7 +218,8 @@ impl Liver {
             Ok(client) => client,
             Err(e) => {
                 log::error!("{} failed to build AcFun API client: {}", self, e);
-                self.send_message(live_tx, LiveMessage::StopDanmaku(self.live_id.clone()));
+                self.send_message(live_tx, LiveMessage::StopDanmaku(self.live_id.clone()))
+                    .await;
                 return;
             }
         };
@@ -168,18 +241,29 @@ impl Liver {
                         self.live_id,
                         client.live_id()
                     );
-                    self.send_message(live_tx, LiveMessage::StopDanmaku(self.live_id.clone()));
+                    self.send_message(live_tx, LiveMessage::StopDanmaku(self.live_id.clone()))
+                        .await;
                     return;
                 }
                 log::info!("{} start getting danmaku", self);
+                let shutdown = wait_shutdown();
+                tokio::pin!(shutdown);
+                let mut interrupted = false;
                 // 获取弹幕
                 loop {
-                    match time::timeout(TIMEOUT, client.next()).await {
+                    let option = tokio::select! {
+                        option = time::timeout(TIMEOUT, client.next()) => option,
+                        _ = &mut shutdown => {
+                            interrupted = true;
+                            break;
+                        }
+                    };
+                    match option {
                         Ok(option) => match option {
                             Some(result) => match result {
                                 Ok(msg) => match msg {
-                                    Danmaku::ActionSignal(signals) => self.action(signals),
-                                    Danmaku::StateSignal(signals) => self.state(signals),
+                                    Danmaku::ActionSignal(signals) => self.action(signals).await,
+                                    Danmaku::StateSignal(signals) => self.state(signals).await,
                                     Danmaku::NotifySignal(_) => {}
                                 },
                                 Err(e) => {
@@ -199,17 +283,29 @@ impl Liver {
                     log::error!("{} failed to close WebSocket connection: {}", self, e);
                 }
                 log::info!("{} stop getting danmaku", self);
+                // 关闭数据中心时不获取直播总结
+                if interrupted {
+                    self.send_data_message(LiveData::Interrupted).await;
+                    return;
+                }
             }
             Err(e) => {
                 log::warn!("{} failed to build AcFun danmaku client: {}", self, e);
             }
         }
-        // 获取直播总结
+        // 获取直播总结，成功时summary()发送Stop，失败或者关闭数据中心时记录为中断
         let liver = self.clone();
         let _ = tokio::spawn(async move {
-            run_thrice("summary()", || liver.summary()).await;
+            let finished = tokio::select! {
+                finished = run_thrice("summary()", || liver.summary()) => finished,
+                _ = wait_shutdown() => false,
+            };
+            if !finished {
+                liver.send_data_message(LiveData::Interrupted).await;
+            }
         });
-        self.send_message(live_tx, LiveMessage::StopDanmaku(self.live_id.clone()));
+        self.send_message(live_tx, LiveMessage::StopDanmaku(self.live_id.clone()))
+            .await;
     }
 
     async fn live_info(&self, live_data: ApiLiveData, liver_info: UserInfo) -> Result<()> {
@@ -233,9 +329,10 @@ impl Liver {
             save_time: liver_info.save_time,
             title,
         };
-        self.send_data_message(LiveData::LiveInfo(live_info));
-        self.send_data_message(LiveData::LiverInfo(liver_info));
-        self.send_data_message(LiveData::Title(title));
+        self.send_data_message(LiveData::LiveInfo(live_info)).await;
+        self.send_data_message(LiveData::LiverInfo(liver_info))
+            .await;
+        self.send_data_message(LiveData::Title(title)).await;
 
         Ok(())
     }
@@ -250,7 +347,8 @@ impl Liver {
         self.send_data_message(LiveData::Summary(Summary::new(
             self.live_id.clone(),
             summary,
-        )));
+        )))
+        .await;
         time::sleep(SUMMARY_WAIT).await;
         // 获取主播的直播信息
         let info = api_client
@@ -262,7 +360,8 @@ impl Liver {
             // 检查是否意外结束获取弹幕
             if data.live_id == self.live_id.as_str() {
                 let live_tx = LIVE_TX.get().expect("failed to get LIVE_TX");
-                self.send_message(live_tx, LiveMessage::LiveList(vec![info]));
+                self.send_message(live_tx, LiveMessage::LiveList(vec![info]))
+                    .await;
             }
         }
         let mut medal_name = None;
@@ -282,91 +381,104 @@ impl Liver {
             fans_count,
             medal_name,
             medal_count,
-        ));
+        ))
+        .await;
         // 发送停止sql运行的消息
-        self.send_data_message(LiveData::Stop);
+        self.send_data_message(LiveData::Stop).await;
 
         Ok(())
     }
 
-    fn action(&self, signals: Vec<ActionSignal>) {
+    async fn action(&self, signals: Vec<ActionSignal>) {
         for signal in signals {
             match signal {
                 ActionSignal::Comment(comment) => {
                     self.send_data_message(LiveData::Comment(Comment::new(
                         self.live_id.clone(),
                         comment,
-                    )));
+                    )))
+                    .await;
                 }
                 ActionSignal::FollowAuthor(follow) => {
                     self.send_data_message(LiveData::Follow(Follow::new(
                         self.live_id.clone(),
                         follow,
-                    )));
+                    )))
+                    .await;
                 }
                 ActionSignal::Gift(gift) => {
-                    self.send_data_message(LiveData::Gift(Gift::new(self.live_id.clone(), gift)));
+                    self.send_data_message(LiveData::Gift(Gift::new(self.live_id.clone(), gift)))
+                        .await;
                 }
                 ActionSignal::JoinClub(join_club) => {
                     self.send_data_message(LiveData::JoinClub(JoinClub::new(
                         self.live_id.clone(),
                         join_club,
-                    )));
+                    )))
+                    .await;
                 }
                 _ => {}
             }
         }
     }
 
-    fn state(&self, signals: Vec<StateSignal>) {
+    async fn state(&self, signals: Vec<StateSignal>) {
         for signal in signals {
             match signal {
                 StateSignal::AcFunDisplayInfo(info) => {
-                    self.send_data_message(LiveData::Banana(Some(info.banana_count)));
+                    self.send_data_message(LiveData::Banana(Some(info.banana_count)))
+                        .await;
                 }
                 StateSignal::DisplayInfo(info) => {
-                    self.send_data_message(LiveData::WatchingCount(info));
+                    self.send_data_message(LiveData::WatchingCount(info)).await;
                 }
                 StateSignal::RedpackList(list) => {
-                    self.send_data_message(LiveData::Redpack(list));
+                    self.send_data_message(LiveData::Redpack(list)).await;
                 }
                 StateSignal::ChatCall(call) => {
-                    self.send_data_message(LiveData::ChatCall(call.into()));
+                    self.send_data_message(LiveData::ChatCall(call.into()))
+                        .await;
                 }
                 StateSignal::ChatReady(ready) => {
                     self.send_data_message(LiveData::ChatReady(ChatReady::new(
                         self.live_id.clone(),
                         ready,
-                    )));
+                    )))
+                    .await;
                 }
                 StateSignal::ChatEnd(end) => {
                     self.send_data_message(LiveData::ChatEnd(ChatEnd::new(
                         self.live_id.clone(),
                         end,
-                    )));
+                    )))
+                    .await;
                 }
                 StateSignal::AuthorChatCall(call) => {
                     self.send_data_message(LiveData::AuthorChatCall(AuthorChatCall::new(
                         self.live_id.clone(),
                         call,
-                    )));
+                    )))
+                    .await;
                 }
                 StateSignal::AuthorChatReady(ready) => {
                     self.send_data_message(LiveData::AuthorChatReady(AuthorChatReady::new(
                         self.live_id.clone(),
                         ready,
-                    )));
+                    )))
+                    .await;
                 }
                 StateSignal::AuthorChatEnd(end) => {
                     self.send_data_message(LiveData::AuthorChatEnd(AuthorChatEnd::new(
                         self.live_id.clone(),
                         end,
-                    )));
+                    )))
+                    .await;
                 }
                 StateSignal::AuthorChatChangeSoundConfig(config) => {
                     self.send_data_message(LiveData::AuthorChatChangeSoundConfig(
                         AuthorChatChangeSoundConfig::new(self.live_id.clone(), config),
-                    ));
+                    ))
+                    .await;
                 }
                 _ => {}
             }
@@ -396,7 +508,7 @@ pub async fn all_lives() {
         };
         match api_client.get_live_list(1_000_000, 0).await {
             Ok(list) => {
-                if let Err(e) = live_tx.send(LiveMessage::LiveList(list.live_list)) {
+                if let Err(e) = live_tx.send(LiveMessage::LiveList(list.live_list)).await {
                     unreachable!("failed to send live list: {}", e);
                 }
             }
@@ -407,10 +519,7 @@ pub async fn all_lives() {
     }
 }
 
-pub async fn all_danmaku(
-    mut live_rx: mpsc::UnboundedReceiver<LiveMessage>,
-    mut config: LiveConfig,
-) {
+pub async fn all_danmaku(mut live_rx: Receiver<LiveMessage>, mut config: LiveConfig) {
     let all_lives_tx = ALL_LIVES_TX.get().expect("failed to get ALL_LIVE_TX");
     // 保存所有直播
     let mut all_lives: AHashSet<LiveId> = AHashSet::new();
@@ -418,9 +527,24 @@ pub async fn all_danmaku(
     let mut all_summaries: AHashSet<LiveId> = AHashSet::new();
     // 保存要记录数据的直播
     let mut lives: AHashMap<LiveId, LiveMapData> = AHashMap::new();
+    // 保存所有还在保存数据的直播，直播结束后可能还在等待直播总结，同一场直播可能重新开始记录
+    let mut workers: Vec<(LiveId, JoinHandle<()>)> = Vec::new();
     while let Some(msg) = live_rx.recv().await {
         match msg {
             LiveMessage::LiveList(list) => {
+                // 去掉已经保存完数据的直播
+                workers = workers
+                    .into_iter()
+                    .filter_map(|(live_id, mut handle)| match (&mut handle).now_or_never() {
+                        Some(result) => {
+                            if let Err(e) = result {
+                                log::error!("[{}] failed to save data: {}", live_id, e);
+                            }
+                            None
+                        }
+                        None => Some((live_id, handle)),
+                    })
+                    .collect();
                 // 不处理直播间列表为空的情况
                 if list.is_empty() {
                     log::warn!("the live list is empty");
@@ -433,7 +557,7 @@ pub async fn all_danmaku(
                         let liver = Liver::new(live_data.live_id.clone(), liver_uid);
                         // 查看是否刚开播的直播
                         if !all_lives.contains(&liver.live_id) {
-                            liver.send_message(
+                            liver.spawn_message(
                                 all_lives_tx,
                                 AllLiveData::Live(Live::new(
                                     liver.liver_uid,
@@ -458,7 +582,7 @@ pub async fn all_danmaku(
                                 // 直播改标题的情况
                                 if live_data.title != old_data.title {
                                     let data_tx = old_data.data_tx.clone();
-                                    liver.send_message(
+                                    liver.spawn_message(
                                         &data_tx,
                                         LiveData::Title(Title::new(
                                             liver.live_id.clone(),
@@ -468,6 +592,7 @@ pub async fn all_danmaku(
                                     let _ = lives.insert(
                                         liver.live_id,
                                         LiveMapData {
+                                            liver_uid,
                                             title: live_data.title,
                                             data_tx,
                                         },
@@ -475,8 +600,15 @@ pub async fn all_danmaku(
                                 }
                             }
                             None => {
+                                // 关闭数据中心后不再记录新的直播
+                                if *SHUTDOWN.1.borrow() {
+                                    continue;
+                                }
                                 let title = live_data.title.clone();
-                                let (data_tx, data_rx) = mpsc::unbounded_channel();
+                                let (data_tx, data_rx) = channel::channel(
+                                    format!("data {}", liver),
+                                    config.channel_capacity(),
+                                );
                                 let mut liver_ = liver.clone();
                                 liver_.data_tx = Some(data_tx.clone());
                                 let user_info = info.user;
@@ -484,10 +616,18 @@ pub async fn all_danmaku(
                                     liver_.danmaku(live_data, user_info).await
                                 });
                                 let live_id_ = liver.live_id.clone();
-                                let _ = tokio::task::spawn_blocking(move || {
+                                let handle = tokio::task::spawn_blocking(move || {
                                     save_data(data_rx, live_id_, liver_uid)
                                 });
-                                let _ = lives.insert(liver.live_id, LiveMapData { title, data_tx });
+                                workers.push((liver.live_id.clone(), handle));
+                                let _ = lives.insert(
+                                    liver.live_id,
+                                    LiveMapData {
+                                        liver_uid,
+                                        title,
+                                        data_tx,
+                                    },
+                                );
                             }
                         }
                     } else {
@@ -501,7 +641,9 @@ pub async fn all_danmaku(
                         let _ = tokio::spawn(async move {
                             run_thrice("all_summary()", || all_summary(live_id.clone())).await;
                             let live_tx = LIVE_TX.get().expect("failed to get LIVE_TX");
-                            if let Err(e) = live_tx.send(LiveMessage::StopSummary(live_id.clone()))
+                            if let Err(e) = live_tx
+                                .send(LiveMessage::StopSummary(live_id.clone()))
+                                .await
                             {
                                 log::error!(
                                     "[{}] failed to send LiveMessage::StopSummary: {}",
@@ -524,19 +666,51 @@ pub async fn all_danmaku(
                     log::warn!("live ID {} wasn't in all_summaries", live_id);
                 }
             }
-            LiveMessage::Command(DataCenterMessage::AddLiver(liver_uid, tool)) => {
-                config.add_liver(liver_uid, tool).await;
-                if let Err(e) = config.save_config().await {
+            LiveMessage::Command(msg, tx) => {
+                let reply = match msg {
+                    DataCenterMessage::AddLiver(liver_uid) => {
+                        config.add_liver(liver_uid).map(DataCenterReply::AddLiver)
+                    }
+                    DataCenterMessage::DeleteLiver(liver_uid) => config
+                        .delete_liver(liver_uid)
+                        .map(DataCenterReply::DeleteLiver),
+                    DataCenterMessage::RecordingLive(liver_uid) => {
+                        Ok(DataCenterReply::RecordingLive(
+                            lives
+                                .iter()
+                                .find(|(_, data)| data.liver_uid == liver_uid)
+                                .map(|(live_id, _)| live_id.to_string()),
+                        ))
+                    }
+                };
+                let saved = match msg {
+                    DataCenterMessage::AddLiver(_) | DataCenterMessage::DeleteLiver(_)
+                        if reply.is_ok() =>
+                    {
+                        config.save_config().await
+                    }
+                    _ => Ok(()),
+                };
+                if let Err(e) = saved {
                     log::error!("failed to save config: {}", e);
+                    let _ = tx.send(Err(MessageError::internal("failed to save config")));
                     return;
                 }
+                if tx.send(reply).is_err() {
+                    log::warn!("failed to reply {:?}", msg);
+                }
             }
-            LiveMessage::Command(DataCenterMessage::DeleteLiver(liver_uid, tool)) => {
-                config.delete_liver(liver_uid, tool).await;
-                if let Err(e) = config.save_config().await {
-                    log::error!("failed to save config: {}", e);
-                    return;
+            LiveMessage::Shutdown(tx) => {
+                // 正在获取弹幕或者直播总结的直播会发送Interrupted，其它直播在发送方都关闭后停止
+                let _ = SHUTDOWN.0.send(true);
+                lives.clear();
+                for (live_id, handle) in workers {
+                    if let Err(e) = handle.await {
+                        log::error!("[{}] failed to wait for saving data: {}", live_id, e);
+                    }
                 }
+                let _ = tx.send(());
+                return;
             }
         }
     }
@@ -544,6 +718,28 @@ pub async fn all_danmaku(
     unreachable!("failed to receive live message");
 }
 
+// 收到SIGTERM或SIGINT后停止获取弹幕，等待所有直播剩下的数据保存完
+pub async fn shutdown() {
+    let mut sigterm = signal(SignalKind::terminate()).expect("failed to listen SIGTERM");
+    let mut sigint = signal(SignalKind::interrupt()).expect("failed to listen SIGINT");
+    tokio::select! {
+        _ = sigterm.recv() => log::info!("receive SIGTERM"),
+        _ = sigint.recv() => log::info!("receive SIGINT"),
+    }
+    log::info!("shutting down, saving the remaining data");
+    let live_tx = LIVE_TX.get().expect("failed to get LIVE_TX");
+    let (tx, rx) = oneshot::channel();
+    if let Err(e) = live_tx.send(LiveMessage::Shutdown(tx)).await {
+        log::error!("failed to send LiveMessage::Shutdown: {}", e);
+        return;
+    }
+    if rx.await.is_err() {
+        log::error!("failed to wait for saving the remaining data");
+        return;
+    }
+    log::info!("all data was saved");
+}
+
 async fn all_summary(live_id: Arc<String>) -> Result<()> {
     let api_client = build_client()
         .await
@@ -561,10 +757,14 @@ async fn all_summary(live_id: Arc<String>) -> Result<()> {
             .await
             .with_context(|| format!("[{}] failed to get summary", live_id))?;
         if summary.data == new_summary.data {
-            all_lives_tx.send(AllLiveData::Summary(live_id.clone(), new_summary))?;
+            all_lives_tx
+                .send(AllLiveData::Summary(live_id.clone(), new_summary))
+                .await?;
             break;
         }
-        all_lives_tx.send(AllLiveData::Summary(live_id.clone(), new_summary))?;
+        all_lives_tx
+            .send(AllLiveData::Summary(live_id.clone(), new_summary))
+            .await?;
         log::info!(
             "[{}] this live is still on, failed to get the final summary, retrying...",
             live_id
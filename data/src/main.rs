@@ -1,8 +1,10 @@
 #![forbid(unsafe_code)]
 
+mod channel;
 mod config;
 mod interval;
 mod live;
+mod migration;
 mod socket;
 mod sql;
 mod sqlite;
@@ -10,7 +12,6 @@ mod sqlite;
 use acfunlivedata_common::config::Config as CommonConfig;
 use anyhow::{bail, Result};
 use rpassword::read_password_from_tty;
-use tokio::sync::mpsc;
 
 const WORKER_THREAD_NUM: usize = 10;
 const MAX_BLOCKING_THREAD: usize = 2048;
@@ -28,42 +29,58 @@ fn main() -> Result<()> {
         bail!("password is empty");
     }
 
-    let (live_tx, live_rx) = mpsc::unbounded_channel();
-    live::LIVE_TX.set(live_tx).expect("failed to set LIVE_TX");
-    let (all_lives_tx, all_lives_rx) = mpsc::unbounded_channel();
-    live::ALL_LIVES_TX
-        .set(all_lives_tx)
-        .expect("failed to set ALL_LIVE_TX");
-    let (gift_tx, gift_rx) = mpsc::unbounded_channel();
-    live::GIFT_TX.set(gift_tx).expect("failed to set GIFT_TX");
-
-    tokio::runtime::Builder::new_multi_thread()
+    let runtime = tokio::runtime::Builder::new_multi_thread()
         .worker_threads(WORKER_THREAD_NUM)
         .thread_name("acfunlivedata worker")
         .enable_all()
         .max_blocking_threads(MAX_BLOCKING_THREAD)
-        .build()?
-        .block_on(async {
-            let config: config::LiveConfig = CommonConfig::new_or_load_config(
-                password.clone(),
-                crate::config::CONFIG_FILE_PATH.as_path(),
-            )
+        .build()?;
+    runtime.block_on(async {
+        let config: config::LiveConfig = CommonConfig::new_or_load_config(
+            password.clone(),
+            crate::config::CONFIG_FILE_PATH.as_path(),
+        )
+        .await
+        .expect("failed to load config");
+        sqlite::create_db_dir()
             .await
-            .expect("failed to load config");
-            sqlite::create_db_dir()
-                .await
-                .expect("failed to create database directory");
+            .expect("failed to create database directory");
+
+        let capacity = config.channel_capacity();
+        let (live_tx, live_rx) = channel::channel("live", capacity);
+        live::LIVE_TX.set(live_tx).expect("failed to set LIVE_TX");
+        let (all_lives_tx, all_lives_rx) = channel::channel("all lives", capacity);
+        live::ALL_LIVES_TX
+            .set(all_lives_tx)
+            .expect("failed to set ALL_LIVE_TX");
+        let (gift_tx, gift_rx) = channel::channel("gift", capacity);
+        live::GIFT_TX.set(gift_tx).expect("failed to set GIFT_TX");
+
+        let all_lives_handle = tokio::task::spawn_blocking(|| sqlite::all_lives(all_lives_rx));
+        let gift_handle = tokio::task::spawn_blocking(|| sqlite::gift_info(gift_rx));
 
-            tokio::task::spawn_blocking(|| sqlite::all_lives(all_lives_rx));
-            tokio::task::spawn_blocking(|| sqlite::gift_info(gift_rx));
+        tokio::select! {
+            _ = socket::message(password) => {}
+            _ = interval::send_tick() => {}
+            _ = channel::monitor() => {}
+            _ = live::all_lives() => {}
+            _ = live::all_danmaku(live_rx, config) => {}
+            _ = live::shutdown() => {}
+        }
 
-            tokio::select! {
-                _ = socket::message(password) => {}
-                _ = interval::send_tick() => {}
-                _ = live::all_lives() => {}
-                _ = live::all_danmaku(live_rx, config) => {}
-            }
-        });
+        // 关闭channel，等待队列里剩下的live和gift_info保存完
+        live::ALL_LIVES_TX
+            .get()
+            .expect("failed to get ALL_LIVE_TX")
+            .close();
+        live::GIFT_TX.get().expect("failed to get GIFT_TX").close();
+        if let Err(e) = all_lives_handle.await {
+            log::error!("failed to wait for saving lives: {}", e);
+        }
+        if let Err(e) = gift_handle.await {
+            log::error!("failed to wait for saving gift info: {}", e);
+        }
+    });
 
     Ok(())
 }
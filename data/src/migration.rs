@@ -0,0 +1,141 @@
+use crate::sql::*;
+use anyhow::{bail, Result};
+use rusqlite::{params, Connection};
+
+// 每个版本的迁移由若干SQL组成，下标加1就是迁移后的user_version
+// 修改表结构只能在最后添加新版本，不能修改已有的版本
+pub type Migration = &'static [&'static str];
+
+pub const LIVE_MIGRATIONS: &[Migration] = &[&[CREATE_LIVE]];
+
+pub const GIFT_INFO_MIGRATIONS: &[Migration] = &[&[CREATE_GIFT_INFO]];
+
+pub const LIVER_MIGRATIONS: &[Migration] = &[
+    &[
+        CREATE_LIVE_INFO,
+        CREATE_TITLE,
+        CREATE_LIVER_INFO,
+        CREATE_SUMMARY,
+        CREATE_COMMENT,
+        CREATE_FOLLOW,
+        CREATE_GIFT,
+        CREATE_JOIN_CLUB,
+        CREATE_WATCHING_COUNT,
+        CREATE_REDPACK,
+        CREATE_CHAT_CALL,
+        CREATE_CHAT_READY,
+        CREATE_CHAT_END,
+        CREATE_AUTHOR_CHAT_CALL,
+        CREATE_AUTHOR_CHAT_READY,
+        CREATE_AUTHOR_CHAT_END,
+        CREATE_AUTHOR_CHAT_CHANGE_SOUND_CONFIG,
+    ],
+    // 记录数据中心关闭时没有获取到直播总结的直播
+    &[CREATE_INTERRUPTED],
+];
+
+#[inline]
+pub fn user_version(conn: &Connection) -> Result<usize> {
+    Ok(conn.query_row(SELECT_USER_VERSION, params![], |r| r.get::<_, i64>(0))? as usize)
+}
+
+// 没有user_version的旧数据库版本为0，第一个版本的SQL都是IF NOT EXISTS，可以直接升级
+pub fn migrate(conn: &Connection, migrations: &[Migration]) -> Result<()> {
+    let version = user_version(conn)?;
+    if version > migrations.len() {
+        bail!(
+            "the database version {} is newer than the latest version {}",
+            version,
+            migrations.len()
+        );
+    }
+    for (i, migration) in migrations.iter().enumerate().skip(version) {
+        let tx = conn.unchecked_transaction()?;
+        for sql in migration.iter() {
+            tx.execute_batch(sql)?;
+        }
+        tx.execute_batch(&format!("{}{};", SET_USER_VERSION, i + 1))?;
+        tx.commit()?;
+        log::info!("migrate the database to version {}", i + 1);
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 建立某个版本的数据库
+    fn fixture(migrations: &[Migration], version: usize) -> Result<Connection> {
+        let conn = Connection::open_in_memory()?;
+        migrate(&conn, &migrations[..version])?;
+        assert_eq!(user_version(&conn)?, version);
+
+        Ok(conn)
+    }
+
+    fn table_count(conn: &Connection) -> Result<i64> {
+        Ok(conn.query_row(
+            "SELECT COUNT(*) FROM sqlite_master WHERE type = 'table';",
+            params![],
+            |r| r.get(0),
+        )?)
+    }
+
+    fn upgrade_from_every_version(migrations: &[Migration]) -> Result<()> {
+        let latest = fixture(migrations, migrations.len())?;
+        for version in 0..=migrations.len() {
+            let conn = fixture(migrations, version)?;
+            migrate(&conn, migrations)?;
+            assert_eq!(user_version(&conn)?, migrations.len());
+            assert_eq!(table_count(&conn)?, table_count(&latest)?);
+            // 再次迁移不会有变化
+            migrate(&conn, migrations)?;
+            assert_eq!(user_version(&conn)?, migrations.len());
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_upgrade() -> Result<()> {
+        upgrade_from_every_version(LIVE_MIGRATIONS)?;
+        upgrade_from_every_version(GIFT_INFO_MIGRATIONS)?;
+        upgrade_from_every_version(LIVER_MIGRATIONS)
+    }
+
+    // 加入迁移之前建立的数据库已经有表，但是user_version为0
+    #[test]
+    fn test_upgrade_unversioned() -> Result<()> {
+        let conn = Connection::open_in_memory()?;
+        for sql in LIVER_MIGRATIONS[0] {
+            conn.execute_batch(sql)?;
+        }
+        conn.execute(
+            "INSERT INTO comment (live_id, send_time, content) VALUES (?, ?, ?);",
+            params!["abc", 1, "comment"],
+        )?;
+        assert_eq!(user_version(&conn)?, 0);
+        migrate(&conn, LIVER_MIGRATIONS)?;
+        assert_eq!(user_version(&conn)?, LIVER_MIGRATIONS.len());
+        let content: String =
+            conn.query_row("SELECT content FROM comment;", params![], |r| r.get(0))?;
+        assert_eq!(content, "comment");
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_newer_version() -> Result<()> {
+        let conn = Connection::open_in_memory()?;
+        conn.execute_batch(&format!(
+            "{}{};",
+            SET_USER_VERSION,
+            LIVE_MIGRATIONS.len() + 1
+        ))?;
+        assert!(migrate(&conn, LIVE_MIGRATIONS).is_err());
+
+        Ok(())
+    }
+}
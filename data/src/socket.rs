@@ -1,6 +1,8 @@
 use crate::live::{LiveMessage, LIVE_TX};
-use acfunlivedata_common::message::{DataCenterMessage, MessageSocket, DATA_CENTER_SOCKET};
-use anyhow::bail;
+use acfunlivedata_common::message::{
+    DataCenterMessage, MessageError, MessageSocket, DATA_CENTER_SOCKET,
+};
+use tokio::sync::oneshot;
 
 pub async fn message(password: String) {
     let live_tx = LIVE_TX.get().expect("failed to get LIVE_TX");
@@ -10,10 +12,14 @@ pub async fn message(password: String) {
     loop {
         if let Err(e) = server
             .listen(|m| async move {
-                if let Err(e) = live_tx.send(LiveMessage::Command(m)) {
-                    bail!("failed to send LiveMessage: {}", e);
+                let (tx, rx) = oneshot::channel();
+                if let Err(e) = live_tx.send(LiveMessage::Command(m, tx)).await {
+                    return Err(MessageError::internal(format!(
+                        "failed to send LiveMessage: {}",
+                        e
+                    )));
                 }
-                Ok(())
+                rx.await.map_err(MessageError::internal)?
             })
             .await
         {
@@ -134,6 +134,15 @@ pub const REPLACE_SUMMARY: &str = r"INSERT OR REPLACE INTO summary
 (live_id, save_time, duration, like_count, watch_total_count, watch_online_max_count, banana_count)
 VALUES (:live_id, :save_time, :duration, :like_count, :watch_total_count, :watch_online_max_count, :banana_count);";
 
+pub const CREATE_INTERRUPTED: &str = r"CREATE TABLE IF NOT EXISTS interrupted (
+live_id TEXT NOT NULL,
+save_time INTEGER NOT NULL
+);
+CREATE UNIQUE INDEX IF NOT EXISTS interrupted_live_id_index ON interrupted (live_id);";
+pub const INSERT_INTERRUPTED: &str = r"INSERT OR IGNORE INTO interrupted
+(live_id, save_time)
+VALUES (:live_id, :save_time);";
+
 pub const CREATE_COMMENT: &str = r"CREATE TABLE IF NOT EXISTS comment (
 live_id TEXT NOT NULL,
 send_time INTEGER NOT NULL,
@@ -369,3 +378,16 @@ CREATE UNIQUE INDEX IF NOT EXISTS author_chat_change_sound_config_save_time_inde
 pub const INSERT_AUTHOR_CHAT_CHANGE_SOUND_CONFIG: &str = r"INSERT INTO author_chat_change_sound_config
 (author_chat_id, live_id, save_time, sound_config_change_type)
 VALUES (:author_chat_id, :live_id, :save_time, :sound_config_change_type);";
+
+// WAL模式下后端读取时不会阻塞写入，synchronous为NORMAL时只在checkpoint时fsync
+pub const WAL: &str = r"PRAGMA journal_mode = WAL;
+PRAGMA synchronous = NORMAL;
+PRAGMA wal_autocheckpoint = 1000;";
+pub const CHECKPOINT: &str = "PRAGMA wal_checkpoint(TRUNCATE);";
+
+pub const BEGIN: &str = "BEGIN;";
+pub const COMMIT: &str = "COMMIT;";
+
+pub const SELECT_USER_VERSION: &str = "PRAGMA user_version;";
+// PRAGMA不能绑定参数，后面直接接上版本号
+pub const SET_USER_VERSION: &str = "PRAGMA user_version = ";
@@ -1,6 +1,8 @@
 use crate::{
+    channel::Receiver,
     interval::WATCH_INTERVAL_TX,
     live::{AllLiveData, LiveData},
+    migration::*,
     sql::*,
 };
 use acfunliveapi::response::Gift as ApiGift;
@@ -9,8 +11,16 @@ use ahash::AHashSet;
 use anyhow::Result;
 use once_cell::sync::Lazy;
 use rusqlite::{named_params, Connection, OpenFlags, OptionalExtension};
-use std::{path::Path, sync::Arc};
-use tokio::sync::mpsc;
+use std::{path::Path, sync::Arc, time::Duration};
+use tokio::{
+    runtime::Handle,
+    time::{self, Instant},
+};
+
+// 每个事务最多写入的数据数量
+const BATCH_SIZE: usize = 500;
+// 事务最长的等待提交时间
+const BATCH_INTERVAL: Duration = Duration::from_millis(1000);
 
 static OPEN_FLAGS: Lazy<OpenFlags> = Lazy::new(|| {
     OpenFlags::SQLITE_OPEN_READ_WRITE
@@ -26,14 +36,17 @@ pub async fn create_db_dir() -> Result<()> {
 
 #[inline]
 fn connect<P: AsRef<Path>>(path: P) -> Result<Connection> {
-    Ok(Connection::open_with_flags(path, *OPEN_FLAGS)?)
+    let conn = Connection::open_with_flags(path, *OPEN_FLAGS)?;
+    conn.busy_timeout(BUSY_TIMEOUT)?;
+    conn.execute_batch(WAL)?;
+
+    Ok(conn)
 }
 
-pub fn all_lives(mut all_lives_rx: mpsc::UnboundedReceiver<AllLiveData>) {
+pub fn all_lives(mut all_lives_rx: Receiver<AllLiveData>) {
     let conn = connect(&*ACFUN_LIVE_DATABASE)
         .unwrap_or_else(|e| panic!("failed to connect {}: {}", ACFUN_LIVE_DATABASE_NAME, e));
-    conn.execute_batch(CREATE_LIVE)
-        .expect("failed to create live table");
+    migrate(&conn, LIVE_MIGRATIONS).expect("failed to migrate live table");
     let mut live_stmt = conn
         .prepare(INSERT_LIVE)
         .expect("failed to prepare live statement");
@@ -83,14 +96,13 @@ pub fn all_lives(mut all_lives_rx: mpsc::UnboundedReceiver<AllLiveData>) {
             }
         }
     }
-    unreachable!("failed to receive AllLiveData");
+    log::info!("stop live sql");
 }
 
-pub fn gift_info(mut gift_rx: mpsc::UnboundedReceiver<Vec<ApiGift>>) {
+pub fn gift_info(mut gift_rx: Receiver<Vec<ApiGift>>) {
     let conn = connect(&*GIFT_DATABASE)
         .unwrap_or_else(|e| panic!("failed to connect {}: {}", GIFT_DATABASE_NAME, e));
-    conn.execute_batch(CREATE_GIFT_INFO)
-        .expect("failed to create gift_info table");
+    migrate(&conn, GIFT_INFO_MIGRATIONS).expect("failed to migrate gift_info table");
     let mut stmt = conn
         .prepare(INSERT_GIFT_INFO)
         .expect("failed to prepare gift_info statement");
@@ -123,10 +135,10 @@ pub fn gift_info(mut gift_rx: mpsc::UnboundedReceiver<Vec<ApiGift>>) {
             }
         }
     }
-    unreachable!("failed to receive Vec<Gift>");
+    log::info!("stop gift sql");
 }
 
-pub fn save_data(mut data_rx: mpsc::UnboundedReceiver<LiveData>, live_id: LiveId, liver_uid: i64) {
+pub fn save_data(mut data_rx: Receiver<LiveData>, live_id: LiveId, liver_uid: i64) {
     let path = liver_db_path(liver_uid);
     let conn = match Conn::new(&path, live_id.clone(), liver_uid) {
         Ok(conn) => conn,
@@ -143,7 +155,7 @@ pub fn save_data(mut data_rx: mpsc::UnboundedReceiver<LiveData>, live_id: LiveId
     };
     if let Err(e) = conn.create_livers_table() {
         log::error!(
-            "[{}] [{}] failed to create livers table: {}",
+            "[{}] [{}] failed to migrate livers tables: {}",
             live_id,
             liver_uid,
             e
@@ -151,12 +163,30 @@ pub fn save_data(mut data_rx: mpsc::UnboundedReceiver<LiveData>, live_id: LiveId
         return;
     }
 
+    let handle = Handle::current();
+    let mut batch = Batch::default();
     let mut interval_rx = WATCH_INTERVAL_TX.subscribe();
     let mut banana = None;
     let mut max_watch = None;
     let mut redpack_id: AHashSet<String> = AHashSet::new();
+    let mut has_summary = false;
     log::info!("[{}] [{}] start saving data", live_id, liver_uid);
-    while let Some(data) = data_rx.blocking_recv() {
+    loop {
+        let data = match batch.deadline {
+            Some(deadline) => match handle.block_on(time::timeout_at(deadline, data_rx.recv())) {
+                Ok(data) => data,
+                Err(_) => {
+                    batch.commit(&conn);
+                    continue;
+                }
+            },
+            None => data_rx.blocking_recv(),
+        };
+        let data = match data {
+            Some(data) => data,
+            None => break,
+        };
+        batch.begin(&conn);
         match data {
             LiveData::LiveInfo(info) => conn.live_info(info),
             LiveData::Title(title) => conn.title(title),
@@ -164,7 +194,10 @@ pub fn save_data(mut data_rx: mpsc::UnboundedReceiver<LiveData>, live_id: LiveId
             LiveData::UpdateCount(live_id, fans_count, medal_name, medal_count) => {
                 conn.update_count(live_id, fans_count, medal_name, medal_count);
             }
-            LiveData::Summary(summary) => conn.summary(summary, max_watch, banana.clone()),
+            LiveData::Summary(summary) => {
+                has_summary = true;
+                conn.summary(summary, max_watch, banana.clone());
+            }
             LiveData::Comment(comment) => conn.comment(comment),
             LiveData::Follow(follow) => conn.follow(follow),
             LiveData::Gift(gift) => conn.gift(gift),
@@ -211,11 +244,25 @@ pub fn save_data(mut data_rx: mpsc::UnboundedReceiver<LiveData>, live_id: LiveId
                 conn.author_chat_change_sound_config(config)
             }
             LiveData::Stop => {
+                batch.commit(&conn);
+                conn.checkpoint();
                 log::info!("[{}] [{}] stop saving data", live_id, liver_uid);
                 return;
             }
+            LiveData::Interrupted => {
+                if !has_summary {
+                    conn.interrupted();
+                }
+                batch.commit(&conn);
+                conn.checkpoint();
+                log::info!("[{}] [{}] interrupt saving data", live_id, liver_uid);
+                return;
+            }
         }
+        batch.add(&conn);
     }
+    batch.commit(&conn);
+    conn.checkpoint();
     log::warn!(
         "[{}] [{}] stop saving data accidentally",
         live_id,
@@ -223,6 +270,71 @@ pub fn save_data(mut data_rx: mpsc::UnboundedReceiver<LiveData>, live_id: LiveId
     );
 }
 
+// 数据达到BATCH_SIZE或者事务超过BATCH_INTERVAL时提交
+#[derive(Debug, Default)]
+struct Batch {
+    // 已经BEGIN但还没有COMMIT
+    open: bool,
+    pending: usize,
+    deadline: Option<Instant>,
+}
+
+impl Batch {
+    #[inline]
+    fn begin(&mut self, conn: &Conn) {
+        if !self.open && conn.begin() {
+            self.open = true;
+            self.deadline = Some(Instant::now() + BATCH_INTERVAL);
+        }
+    }
+
+    #[inline]
+    fn add(&mut self, conn: &Conn) {
+        if self.rolled_back(conn) {
+            return;
+        }
+        self.pending += 1;
+        if self.pending >= BATCH_SIZE {
+            self.commit(conn);
+        }
+    }
+
+    // 提交失败的话事务保持打开，之后再重试
+    #[inline]
+    fn commit(&mut self, conn: &Conn) {
+        if !self.open || self.rolled_back(conn) {
+            return;
+        }
+        if conn.commit() {
+            self.reset();
+        } else {
+            self.deadline = Some(Instant::now() + BATCH_INTERVAL);
+        }
+    }
+
+    // SQLite出错时（如SQLITE_FULL、SQLITE_IOERR）可能自动回滚事务，这时事务里的数据都丢失了
+    fn rolled_back(&mut self, conn: &Conn) -> bool {
+        if self.open && conn.conn.is_autocommit() {
+            log::error!(
+                "{} transaction was rolled back by SQLite, lost the data of {} messages",
+                conn,
+                self.pending
+            );
+            self.reset();
+            return true;
+        }
+
+        false
+    }
+
+    #[inline]
+    fn reset(&mut self) {
+        self.open = false;
+        self.pending = 0;
+        self.deadline = None;
+    }
+}
+
 macro_rules! cached_stmt {
     ($conn:expr, $sql:expr, $s:expr) => {
         match ($conn).conn.prepare_cached(($sql)) {
@@ -252,27 +364,40 @@ impl Conn {
         })
     }
 
+    #[inline]
     fn create_livers_table(&self) -> Result<()> {
-        self.conn.execute_batch(CREATE_LIVE_INFO)?;
-        self.conn.execute_batch(CREATE_TITLE)?;
-        self.conn.execute_batch(CREATE_LIVER_INFO)?;
-        self.conn.execute_batch(CREATE_SUMMARY)?;
-        self.conn.execute_batch(CREATE_COMMENT)?;
-        self.conn.execute_batch(CREATE_FOLLOW)?;
-        self.conn.execute_batch(CREATE_GIFT)?;
-        self.conn.execute_batch(CREATE_JOIN_CLUB)?;
-        self.conn.execute_batch(CREATE_WATCHING_COUNT)?;
-        self.conn.execute_batch(CREATE_REDPACK)?;
-        self.conn.execute_batch(CREATE_CHAT_CALL)?;
-        self.conn.execute_batch(CREATE_CHAT_READY)?;
-        self.conn.execute_batch(CREATE_CHAT_END)?;
-        self.conn.execute_batch(CREATE_AUTHOR_CHAT_CALL)?;
-        self.conn.execute_batch(CREATE_AUTHOR_CHAT_READY)?;
-        self.conn.execute_batch(CREATE_AUTHOR_CHAT_END)?;
-        self.conn
-            .execute_batch(CREATE_AUTHOR_CHAT_CHANGE_SOUND_CONFIG)?;
+        migrate(&self.conn, LIVER_MIGRATIONS)
+    }
 
-        Ok(())
+    #[inline]
+    fn begin(&self) -> bool {
+        if !self.conn.is_autocommit() {
+            return true;
+        }
+        if let Err(e) = self.conn.execute_batch(BEGIN) {
+            log::error!("{} failed to begin transaction: {}", self, e);
+            return false;
+        }
+
+        true
+    }
+
+    #[inline]
+    fn commit(&self) -> bool {
+        if let Err(e) = self.conn.execute_batch(COMMIT) {
+            log::error!("{} failed to commit transaction: {}", self, e);
+            return false;
+        }
+
+        true
+    }
+
+    // 直播结束时把WAL写回数据库并清空WAL文件
+    #[inline]
+    fn checkpoint(&self) {
+        if let Err(e) = self.conn.execute_batch(CHECKPOINT) {
+            log::error!("{} failed to checkpoint: {}", self, e);
+        }
     }
 
     fn live_info(&self, info: LiveInfo) {
@@ -383,6 +508,17 @@ impl Conn {
         }
     }
 
+    // 没有获取到直播总结的直播
+    fn interrupted(&self) {
+        let mut stmt = cached_stmt!(self, INSERT_INTERRUPTED, "interrupted");
+        if let Err(e) = stmt.execute(named_params! {
+            ":live_id": self.live_id,
+            ":save_time": chrono::Utc::now().timestamp_millis(),
+        }) {
+            log::error!("{} failed to insert interrupted: {}", self, e);
+        }
+    }
+
     fn comment(&self, comment: Comment) {
         let mut stmt = cached_stmt!(self, INSERT_COMMENT, "comment");
         if let Err(e) = stmt.execute(named_params! {
@@ -621,3 +757,46 @@ impl std::fmt::Display for Conn {
         write!(f, "[{}] [{}]", self.live_id, self.liver_uid)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rusqlite::params;
+
+    #[test]
+    fn test_rolled_back() -> Result<()> {
+        let conn = Conn::new(":memory:", Arc::new("test".to_string()), 1)?;
+        conn.create_livers_table()?;
+        let comment = |send_time| Comment {
+            live_id: conn.live_id.clone(),
+            send_time,
+            user_info: None,
+            content: "test".to_string(),
+        };
+        let count = || -> Result<i64> {
+            Ok(conn
+                .conn
+                .query_row("SELECT COUNT(*) FROM comment", params![], |r| r.get(0))?)
+        };
+        let mut batch = Batch::default();
+        batch.begin(&conn);
+        conn.comment(comment(1));
+        batch.add(&conn);
+        // 模拟SQLite自动回滚事务
+        conn.conn.execute_batch("ROLLBACK")?;
+        batch.commit(&conn);
+        assert!(!batch.open);
+        assert_eq!(batch.pending, 0);
+        assert_eq!(count()?, 0);
+
+        batch.begin(&conn);
+        assert!(batch.open);
+        conn.comment(comment(2));
+        batch.add(&conn);
+        batch.commit(&conn);
+        assert!(!batch.open);
+        assert_eq!(count()?, 1);
+
+        Ok(())
+    }
+}
@@ -1,20 +1,12 @@
 #![forbid(unsafe_code)]
 
 use acfunlivedata_common::message::{
-    BackendMessage, DataCenterMessage, MessageSocket, ToolMessage, BACKEND_SOCKET,
-    DATA_CENTER_SOCKET, TOOL_PASSWORD, TOOL_SOCKET,
+    BackendMessage, BackendReply, DataCenterMessage, DataCenterReply, MessageSocket,
+    BACKEND_SOCKET, DATA_CENTER_SOCKET,
 };
 use anyhow::{bail, Result};
-use once_cell::sync::Lazy;
 use rpassword::read_password_from_tty;
-use std::{sync::Arc, time::Duration};
 use structopt::StructOpt;
-use tokio::{sync::Mutex, time};
-
-const SLEEP: Duration = Duration::from_secs(2);
-const SERVER_RUN_TIME: Duration = Duration::from_secs(10);
-
-static NUM: Lazy<Arc<Mutex<usize>>> = Lazy::new(|| Arc::new(Mutex::new(0)));
 
 #[derive(Clone, Debug, StructOpt)]
 #[structopt(name = "acfunlivedata-tool", about = "A tool for acfunlivedata.")]
@@ -40,7 +32,6 @@ struct Opt {
 #[tokio::main]
 async fn main() -> Result<()> {
     let opt = Opt::from_args();
-    let opt_num = opt.add.len() + opt.del.len();
     if opt.add.iter().chain(opt.del.iter()).any(|i| *i <= 0) {
         bail!("some liver uids in the option are less than 1");
     }
@@ -54,117 +45,134 @@ async fn main() -> Result<()> {
         bail!("backend password is empty");
     }
 
-    let _ = tokio::spawn(async move {
-        time::sleep(SLEEP).await;
-        let data_client: MessageSocket<_, DataCenterMessage> =
-            MessageSocket::new_client(DATA_CENTER_SOCKET, data_center_password);
-        for liver_uid in &opt.add {
-            data_client
-                .send(&DataCenterMessage::AddLiver(*liver_uid, true))
-                .await
-                .expect("failed to send DataCenterMessage::AddLiver");
-        }
-        for liver_uid in &opt.del {
-            data_client
-                .send(&DataCenterMessage::DeleteLiver(*liver_uid, true))
-                .await
-                .expect("failed to send DataCenterMessage::DeleteLiver");
-        }
+    let data_client: MessageSocket<_, DataCenterMessage> =
+        MessageSocket::new_client(DATA_CENTER_SOCKET, data_center_password);
+    let backend_client: MessageSocket<_, BackendMessage> =
+        MessageSocket::new_client(BACKEND_SOCKET, backend_password);
+    let mut failed = false;
 
-        let backend_client: MessageSocket<_, BackendMessage> =
-            MessageSocket::new_client(BACKEND_SOCKET, backend_password);
-        for liver_uid in &opt.add {
-            backend_client
-                .send(&BackendMessage::AddLiver(*liver_uid))
-                .await
-                .expect("failed to send BackendMessage::AddLiver");
+    for liver_uid in &opt.add {
+        match data_client
+            .request(&DataCenterMessage::AddLiver(*liver_uid))
+            .await
+        {
+            Ok(DataCenterReply::AddLiver(true)) => {
+                println!("liver uid {} is already in acfunlivedata config", liver_uid);
+            }
+            Ok(DataCenterReply::AddLiver(false)) => {
+                println!(
+                    "add liver uid {} in acfunlivedata config successfully",
+                    liver_uid
+                );
+            }
+            Ok(reply) => {
+                failed = true;
+                println!("unexpected reply from acfunlivedata: {:?}", reply);
+            }
+            Err(e) => {
+                failed = true;
+                println!(
+                    "failed to add liver uid {} in acfunlivedata config: {}",
+                    liver_uid, e
+                );
+            }
         }
-        for liver_uid in &opt.del {
-            backend_client
-                .send(&BackendMessage::DeleteLiver(*liver_uid))
-                .await
-                .expect("failed to send BackendMessage::DeleteLiver");
+    }
+    for liver_uid in &opt.del {
+        match data_client
+            .request(&DataCenterMessage::DeleteLiver(*liver_uid))
+            .await
+        {
+            Ok(DataCenterReply::DeleteLiver(true)) => {
+                println!(
+                    "delete liver uid {} in acfunlivedata config successfully",
+                    liver_uid
+                );
+            }
+            Ok(DataCenterReply::DeleteLiver(false)) => {
+                println!("liver uid {} is not in acfunlivedata config", liver_uid);
+            }
+            Ok(reply) => {
+                failed = true;
+                println!("unexpected reply from acfunlivedata: {:?}", reply);
+            }
+            Err(e) => {
+                failed = true;
+                println!(
+                    "failed to delete liver uid {} in acfunlivedata config: {}",
+                    liver_uid, e
+                );
+            }
         }
-    });
+    }
 
-    let server = async {
-        let server: MessageSocket<_, ToolMessage> =
-            MessageSocket::new_server(TOOL_SOCKET, TOOL_PASSWORD);
-        server
-            .listen(|m| async move {
-                {
-                    let mut num = NUM.lock().await;
-                    *num += 1;
+    for liver_uid in &opt.add {
+        match backend_client
+            .request(&BackendMessage::AddLiver(*liver_uid))
+            .await
+        {
+            Ok(BackendReply::AddLiver(exist, token)) => {
+                if exist {
+                    println!(
+                        "liver uid {} is already in acfunlivedata-backend config, its tokens are kept",
+                        liver_uid
+                    );
+                } else {
+                    println!(
+                        "add liver uid {} in acfunlivedata-backend config successfully",
+                        liver_uid
+                    );
                 }
-                match m {
-                    ToolMessage::DataCenterAddLiver(liver_uid, exist) => {
-                        if exist {
-                            println!("liver uid {} is already in acfunlivedata config", liver_uid);
-                        } else {
-                            println!(
-                                "add liver uid {} in acfunlivedata config successfully",
-                                liver_uid
-                            );
-                        }
-                    }
-                    ToolMessage::DataCenterDeleteLiver(liver_uid, exist) => {
-                        if exist {
-                            println!(
-                                "delete liver uid {} in acfunlivedata config successfully",
-                                liver_uid
-                            );
-                        } else {
-                            println!("liver uid {} is not in acfunlivedata config", liver_uid);
-                        }
-                    }
-                    ToolMessage::BackendAddLiver(liver_uid, exist, token) => {
-                        if exist {
-                            println!(
-                                "liver uid {} is already in acfunlivedata-backend config",
-                                liver_uid
-                            );
-                            println!(
-                                "liver uid {} already has token\nnow generate new token:\n{}",
-                                liver_uid, token
-                            );
-                        } else {
-                            println!(
-                                "add liver uid {} in acfunlivedata-backend config successfully",
-                                liver_uid
-                            );
-                            println!("generate liver uid {} token:\n{}", liver_uid, token);
-                        }
-                    }
-                    ToolMessage::BackendDeleteLiver(liver_uid, exist) => {
-                        if exist {
-                            println!(
-                                "delete liver uid {} in acfunlivedata-backend config successfully",
-                                liver_uid
-                            );
-                        } else {
-                            println!(
-                                "liver uid {} is not in acfunlivedata-backend config",
-                                liver_uid
-                            );
-                        }
-                    }
-                };
-                Ok(())
-            })
+                if let Some(token) = token {
+                    println!("generate liver uid {} token:\n{}", liver_uid, token);
+                }
+            }
+            Ok(reply) => {
+                failed = true;
+                println!("unexpected reply from acfunlivedata-backend: {:?}", reply);
+            }
+            Err(e) => {
+                failed = true;
+                println!(
+                    "failed to add liver uid {} in acfunlivedata-backend config: {}",
+                    liver_uid, e
+                );
+            }
+        }
+    }
+    for liver_uid in &opt.del {
+        match backend_client
+            .request(&BackendMessage::DeleteLiver(*liver_uid))
             .await
-    };
-    tokio::select! {
-        Err(e) = server => {
-            println!("server listen error: {}", e);
+        {
+            Ok(BackendReply::DeleteLiver(true)) => {
+                println!(
+                    "delete liver uid {} in acfunlivedata-backend config successfully",
+                    liver_uid
+                );
+            }
+            Ok(BackendReply::DeleteLiver(false)) => {
+                println!(
+                    "liver uid {} is not in acfunlivedata-backend config",
+                    liver_uid
+                );
+            }
+            Ok(reply) => {
+                failed = true;
+                println!("unexpected reply from acfunlivedata-backend: {:?}", reply);
+            }
+            Err(e) => {
+                failed = true;
+                println!(
+                    "failed to delete liver uid {} in acfunlivedata-backend config: {}",
+                    liver_uid, e
+                );
+            }
         }
-        _ = time::sleep(SERVER_RUN_TIME) => {}
     }
 
-    let num = NUM.lock().await;
-    if *num != 2 * opt_num {
-        println!(
-            "failed to complete all operations, maybe passwords were wrong or the socket was timeout"
-        );
+    if failed {
+        bail!("failed to complete all operations");
     }
 
     Ok(())